@@ -24,11 +24,14 @@
 use std::{
     marker::PhantomData,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    str::FromStr,
 };
 
 use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize, de::Visitor, ser::SerializeStruct};
 
+use crate::utils::signal::Signal;
+
 mod client;
 pub use client::Client;
 
@@ -63,6 +66,18 @@ pub enum Action {
         /// service id or name
         service: String,
     },
+    /// Send a signal to the given service
+    Signal {
+        /// service id or name
+        service: String,
+        /// signal name or number (ex: SIGHUP, usr1, 10)
+        signal: Signal,
+    },
+    /// Reload the given service, using its `reload_command` or `reload_signal`
+    Reload {
+        /// service id or name
+        service: String,
+    },
     /// Reschedule a service, flag as active and reschedule
     #[clap(visible_alias = "schedule")]
     Reschedule {
@@ -87,6 +102,15 @@ pub enum Action {
         /// Workdir
         #[clap(long, short)]
         workdir: Option<String>,
+        /// User to run the service as (name or uid)
+        #[clap(long, short)]
+        user: Option<String>,
+        /// Group to run the service as (name or gid)
+        #[clap(long, short)]
+        group: Option<String>,
+        /// Supplementary groups (names or gids)
+        #[clap(long = "supplementary-group", short = 'G', value_name = "GROUP")]
+        supplementary_groups: Vec<String>,
         /// Command to run
         #[clap(last = true)]
         command: Vec<String>,
@@ -97,20 +121,43 @@ pub enum Action {
         /// service id or name
         service: String,
     },
+    /// Change the number of instances of a service
+    Scale {
+        /// service id or name
+        service: String,
+        /// Number of instances
+        instances: usize,
+    },
     /// Get statistics on a service
     #[clap(visible_aliases=["statistics", "details"])]
     Stats {
         /// service id or name
         service: Option<String>,
     },
+    /// Show the last runs of a service
+    #[clap(visible_aliases=["runs"])]
+    History {
+        /// service id or name
+        service: String,
+    },
     /// Get statsics from the daemon process
     ///
     /// Used by `statistics` command from cli
     #[command(skip)]
     DaemonStats,
 
+    /// List replicas, as parent service id and instance index
+    ///
+    /// Used by `info` and `stats` commands from cli to group replicas
+    #[command(skip)]
+    ListReplicas,
+
     #[command(skip)]
-    ListLogFiles { service: String },
+    ListLogFiles {
+        service: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run: Option<RunSelector>,
+    },
 
     /// Show service logs
     Log {
@@ -124,10 +171,62 @@ pub enum Action {
         /// Wait for new log-lines
         #[clap(long, short, action = clap::ArgAction::SetTrue)]
         follow: Option<bool>,
+
+        /// Show a scheduled run logs, requires the logger `per_run` mode
+        /// (`last`, `failed` or `n`, `1` being the last run)
+        #[clap(long, short, conflicts_with = "follow")]
+        run: Option<RunSelector>,
     },
 
     /// Get scheduler info
     ShowScheduler,
+
+    /// Get the scheduler pause state and held events
+    ///
+    /// Used by `show-scheduler` command from cli
+    #[command(skip)]
+    SchedulerState,
+
+    /// Hold scheduled runs and watch restarts, running services are not affected
+    PauseScheduler {
+        /// service id or name, pause all services when omitted
+        service: Option<String>,
+    },
+
+    /// Resume scheduled runs and watch restarts
+    ResumeScheduler {
+        /// service id or name, resume all services when omitted
+        service: Option<String>,
+    },
+}
+
+/// Selects a run in a service history
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunSelector {
+    /// Most recent run
+    Last,
+    /// Most recent crashed run
+    Failed,
+    /// `n`th most recent run, starting at 1
+    Nth(usize),
+}
+
+impl FromStr for RunSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last" => Ok(Self::Last),
+            "failed" => Ok(Self::Failed),
+            _ => match s.parse::<usize>() {
+                Ok(n) if n > 0 => Ok(Self::Nth(n)),
+                _ => Err(format!(
+                    "invalid run `{s}`, expecting `last`, `failed` or a number"
+                )),
+            },
+        }
+    }
 }
 
 fn parse_key_val<T, U>(
@@ -290,4 +389,40 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn signal() -> Result<()> {
+        use crate::utils::signal::SIGUSR1;
+
+        let args = Args::try_parse_from(["ppm", "signal", "test", "usr1"])?;
+        assert!(matches!(
+            args.action,
+            Action::Signal { service, signal } if service == "test" && signal == SIGUSR1
+        ));
+        Args::try_parse_from(["ppm", "signal", "test", "SIGNOPE"])
+            .map(|_| ())
+            .expect_err("unknown signal");
+        Ok(())
+    }
+
+    #[test]
+    fn log_run() -> Result<()> {
+        for (arg, run) in [
+            ("last", RunSelector::Last),
+            ("failed", RunSelector::Failed),
+            ("2", RunSelector::Nth(2)),
+        ] {
+            let args = Args::try_parse_from(["ppm", "log", "test", "--run", arg])?;
+            assert!(matches!(args.action, Action::Log { run: Some(r), .. } if r == run));
+        }
+        for arg in ["0", "first"] {
+            Args::try_parse_from(["ppm", "log", "test", "--run", arg])
+                .map(|_| ())
+                .expect_err("invalid run");
+        }
+        Args::try_parse_from(["ppm", "log", "test", "--run", "last", "-f"])
+            .map(|_| ())
+            .expect_err("--run conflicts with --follow");
+        Ok(())
+    }
 }
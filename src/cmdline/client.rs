@@ -42,7 +42,7 @@ use tabled::{
 };
 
 use crate::{
-    monitor::scheduler::SchedulerEvent,
+    monitor::scheduler::{SchedulerEvent, SchedulerState},
     service::{self, ServiceId},
     utils::{self, IS_OUT_COLORED, serializers::tabled::TDisplay},
 };
@@ -96,14 +96,18 @@ impl Client {
     pub fn run(&self, action: &Action) -> Result<()> {
         match action {
             Action::Daemon { .. } => unimplemented!("must be handled before connecting"),
-            Action::List | Action::DaemonStats | Action::ListLogFiles { .. } => {
+            Action::List
+            | Action::DaemonStats
+            | Action::ListReplicas
+            | Action::SchedulerState
+            | Action::ListLogFiles { .. } => {
                 unimplemented!("not available from cmdline")
             }
             Action::Info => {
                 let services_list: HashMap<ServiceId, String> = self.invoke(&Action::List)?;
                 let info: HashMap<ServiceId, service::Info> = self.invoke(&Action::Info)?;
                 let mut keys: Vec<ServiceId> = info.keys().copied().collect();
-                keys.sort();
+                sort_services(&mut keys, &self.list_replicas());
 
                 let data = keys
                     .iter()
@@ -122,7 +126,7 @@ impl Client {
                 let daemon_stats: service::Stats = self.invoke(&Action::DaemonStats)?;
                 let daemon_name = String::from(STATS_DAEMON_NAME);
                 let mut keys: Vec<ServiceId> = stats.keys().copied().collect();
-                keys.sort();
+                sort_services(&mut keys, &self.list_replicas());
 
                 let data = Some(StatsRecord {
                     id: 0,
@@ -143,7 +147,16 @@ impl Client {
                 self.display(Table::new(data));
                 Ok(())
             }
-            action @ (Action::Stop { .. } | Action::Restart { .. } | Action::Remove { .. }) => {
+            action @ Action::History { .. } => {
+                let history: Vec<service::Run> = self.invoke(action)?;
+                self.display(Table::new(history.iter().rev().map(RunRecord::new)));
+                Ok(())
+            }
+            action @ (Action::Stop { .. }
+            | Action::Restart { .. }
+            | Action::Remove { .. }
+            | Action::Reload { .. }
+            | Action::Scale { .. }) => {
                 self.0.set_read_timeout(Some(Duration::from_secs(30)))?;
                 self.invoke(action)
             }
@@ -152,10 +165,32 @@ impl Client {
                 .map(|config| print!("{config}")),
             action @ Action::ShowScheduler => {
                 let services_list: HashMap<ServiceId, String> = self.invoke(&Action::List)?;
-                let sched: Vec<SchedulerEvent> = self.invoke(action)?;
-                let data = sched
+                let events: Vec<SchedulerEvent> = self.invoke(action)?;
+                /* last, older daemons close the connection on unknown actions */
+                let sched: SchedulerState = self
+                    .invoke(&Action::SchedulerState)
+                    .inspect_err(|err| tracing::debug!(?err, "failed to get scheduler state"))
+                    .unwrap_or_default();
+                if sched.paused {
+                    println!("scheduler paused");
+                }
+                if !sched.paused_services.is_empty() {
+                    let names: Vec<&str> = sched
+                        .paused_services
+                        .iter()
+                        .map(|id| services_list.get(id).map_or("?", String::as_str))
+                        .collect();
+                    println!("paused services: {}", names.join(", "));
+                }
+                let data = events
                     .into_iter()
-                    .map(|event| SchedulerEventRecord::new(event, &services_list));
+                    .map(|event| SchedulerEventRecord::new(event, &services_list, false))
+                    .chain(
+                        sched
+                            .held
+                            .into_iter()
+                            .map(|event| SchedulerEventRecord::new(event, &services_list, true)),
+                    );
                 self.display(Table::new(data));
                 Ok(())
             }
@@ -163,9 +198,11 @@ impl Client {
                 service,
                 lines,
                 follow,
+                run,
             } => {
                 let files: Vec<PathBuf> = self.invoke(&Action::ListLogFiles {
                     service: service.clone(),
+                    run: *run,
                 })?;
 
                 if files.is_empty() {
@@ -185,6 +222,15 @@ impl Client {
         }
     }
 
+    /// Replicas of the daemon services, empty if the daemon doesn't support it
+    ///
+    /// Must be invoked last: older daemons close the connection on unknown actions.
+    fn list_replicas(&self) -> HashMap<ServiceId, (ServiceId, usize)> {
+        self.invoke(&Action::ListReplicas)
+            .inspect_err(|err| tracing::debug!(?err, "failed to list replicas"))
+            .unwrap_or_default()
+    }
+
     fn display(&self, mut table: Table) {
         table
             .with(Style::rounded().remove_horizontals())
@@ -200,6 +246,11 @@ impl Client {
     }
 }
 
+/// Sort services by id, replicas are grouped under their parent service
+fn sort_services(keys: &mut [ServiceId], replicas: &HashMap<ServiceId, (ServiceId, usize)>) {
+    keys.sort_by_key(|id| replicas.get(id).copied().unwrap_or((*id, 0)));
+}
+
 #[derive(Tabled)]
 struct InfoRecord<'a, 'b> {
     id: ServiceId,
@@ -241,6 +292,32 @@ fn stats_name(name: &Option<&String>, rec: &StatsRecord) -> String {
     }
 }
 
+#[derive(Tabled)]
+struct RunRecord {
+    trigger: String,
+    #[tabled(display("TDisplay::to_string"), rename = "start time")]
+    start: Instant,
+    #[tabled(display("TDisplay::to_string"), rename = "end time")]
+    end: Option<Instant>,
+    duration: String,
+    status: String,
+    #[tabled(display("display::option", ""))]
+    exit: Option<service::Exit>,
+}
+
+impl RunRecord {
+    pub fn new(run: &service::Run) -> Self {
+        Self {
+            trigger: format!("{:?}", run.trigger).to_lowercase(),
+            start: run.start,
+            end: run.end(),
+            duration: Duration::from_secs(run.duration().as_secs()).to_string(),
+            status: format!("{:?}", run.status),
+            exit: run.exit.clone(),
+        }
+    }
+}
+
 #[derive(Tabled)]
 struct SchedulerEventRecord<'a> {
     #[tabled(display("display::option", ""))]
@@ -253,21 +330,52 @@ struct SchedulerEventRecord<'a> {
 }
 
 impl<'a> SchedulerEventRecord<'a> {
-    pub fn new(event: SchedulerEvent, services_list: &'a HashMap<ServiceId, String>) -> Self {
+    pub fn new(
+        event: SchedulerEvent,
+        services_list: &'a HashMap<ServiceId, String>,
+        held: bool,
+    ) -> Self {
         let id = event.id();
+        let name = Self::event_name(&event);
         Self {
             id,
             name: id.and_then(|id| services_list.get(&id)),
-            event: Self::event_name(&event),
+            event: if held { format!("{name} [held]") } else { name },
             timestamp: *event.instant(),
         }
     }
 
     fn event_name(event: &SchedulerEvent) -> String {
         match event {
-            SchedulerEvent::ServiceSchedule { .. } => "schedule",
+            SchedulerEvent::ServiceSchedule {
+                timezone,
+                skipped,
+                overlap,
+                ..
+            } => {
+                let notes: Vec<String> = timezone
+                    .map(|tz| tz.name().to_string())
+                    .into_iter()
+                    .chain((*skipped != 0).then(|| format!("{skipped} skipped")))
+                    .chain(match overlap {
+                        Some(service::ConcurrencyPolicy::Replace) => Some("last replaced".into()),
+                        Some(service::ConcurrencyPolicy::Queue) => Some("last queued".into()),
+                        _ => None,
+                    })
+                    .collect();
+                return if notes.is_empty() {
+                    String::from("schedule")
+                } else {
+                    format!("schedule ({})", notes.join(", "))
+                };
+            }
+            SchedulerEvent::ServiceQueued { runs: 1, .. } => "queued",
+            SchedulerEvent::ServiceQueued { runs, .. } => return format!("queued ({runs} runs)"),
+            SchedulerEvent::ServiceTimeout { .. } => "timeout",
             SchedulerEvent::ServiceRestart { .. } => "restart",
             SchedulerEvent::WatchServiceRestart { .. } => "watch",
+            SchedulerEvent::ServiceStart { .. } => "start",
+            SchedulerEvent::HealthCheck { .. } => "health check",
             SchedulerEvent::Sysinfo { .. } => "stats",
             SchedulerEvent::ClockCheck { .. } => "clock check",
         }
@@ -282,6 +390,15 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn sort_replicas() {
+        /* `python3.11` is not a replica */
+        let replicas = HashMap::from([(2, (0, 1)), (4, (0, 2))]);
+        let mut keys = vec![4, 3, 2, 1, 0];
+        sort_services(&mut keys, &replicas);
+        assert_eq!(keys, vec![0, 2, 4, 1, 3]);
+    }
+
     #[test]
     fn client() -> Result<()> {
         let listener = TcpListener::bind("127.0.0.1:0")?;
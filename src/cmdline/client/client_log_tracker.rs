@@ -107,6 +107,7 @@ impl<'a> ClientLogTracker<'a> {
                     .client
                     .invoke::<Vec<PathBuf>>(&Action::ListLogFiles {
                         service: self.service.clone(),
+                        run: None,
                     })?
                     .last()
                     && new_file != &self.filename
@@ -158,6 +159,7 @@ impl<'a> ClientLogTracker<'a> {
                 .client
                 .invoke::<Vec<PathBuf>>(&Action::ListLogFiles {
                     service: self.service.clone(),
+                    run: None,
                 })?
                 .last()
                 && new_file != &self.filename
@@ -38,11 +38,11 @@ use crate::{
         Monitor,
         logger::{LOGGER_DAEMON_ID, LOGGER_DAEMON_NAME},
     },
-    service::{Command, Service, ServiceId},
+    service::{Command, Service, ServiceId, Status},
     utils::{InnerRef, wrap_map_iterator},
 };
 
-use super::{Action, ActionResult};
+use super::{Action, ActionResult, RunSelector};
 
 const MAX_CONNECTIONS: usize = 16;
 
@@ -148,6 +148,14 @@ impl Server {
             .or_else(|| monitor.find_by_name(service))
     }
 
+    /// Find services by id, or by name along with their replicas
+    fn find_services(monitor: &Monitor, service: &str) -> Vec<Arc<Service>> {
+        match service.parse::<ServiceId>() {
+            Ok(id) => monitor.get(&id).into_iter().collect(),
+            Err(_) => monitor.find_instances(service),
+        }
+    }
+
     fn is_daemon(service: &String) -> bool {
         match service.parse::<ServiceId>() {
             Ok(id) => id == LOGGER_DAEMON_ID,
@@ -183,6 +191,19 @@ impl Server {
                         .map(|x| (x.id, InnerRef(x, |x| &x.name))),
                 )),
             )?,
+            Action::ListReplicas => {
+                /* snapshot, don't lock the service map while looking for parents */
+                let services: Vec<Arc<Service>> =
+                    monitor.services.iter().map(|x| Arc::clone(&x)).collect();
+                let replicas = services.iter().filter_map(|srv| {
+                    let replica = srv.replica.as_ref()?;
+                    let parent = services
+                        .iter()
+                        .find(|x| x.replica.is_none() && x.name == replica.parent)?;
+                    Some((srv.id, (parent.id, replica.index)))
+                });
+                serde_json::to_writer(stream, &ActionResult::Ok(wrap_map_iterator(replicas)))?
+            }
             Action::Info => serde_json::to_writer(
                 stream,
                 &ActionResult::Ok(wrap_map_iterator(
@@ -206,28 +227,62 @@ impl Server {
                     )?
                 }
             }
+            Action::History { service } => {
+                let service = Server::find_service(monitor, &service)
+                    .with_context(|| format!("no such service \"{service}\""))?;
+                serde_json::to_writer(stream, &ActionResult::Ok(&service.info().history))?
+            }
             Action::DaemonStats => {
                 serde_json::to_writer(stream, &ActionResult::Ok(monitor.stats()))?
             }
             Action::Restart { service } => {
-                let service = Server::find_service(monitor, &service)
-                    .with_context(|| format!("no such service \"{service}\""))?;
-
-                monitor.restart(&service);
+                let services = Server::find_services(monitor, &service);
+                if services.is_empty() {
+                    return Err(anyhow!("no such service \"{service}\""));
+                }
+                for service in services.iter() {
+                    monitor.restart(service);
+                }
                 serde_json::to_writer(stream, &ActionResult::Ok(()))?;
             }
             Action::Reschedule { service } => {
-                let service = Server::find_service(monitor, &service)
-                    .with_context(|| format!("no such service \"{service}\""))?;
-
-                monitor.reschedule(&service);
+                let services = Server::find_services(monitor, &service);
+                if services.is_empty() {
+                    return Err(anyhow!("no such service \"{service}\""));
+                }
+                for service in services.iter() {
+                    monitor.reschedule(service);
+                }
+                serde_json::to_writer(stream, &ActionResult::Ok(()))?;
+            }
+            Action::Signal { service, signal } => {
+                let services = Server::find_services(monitor, &service);
+                if services.is_empty() {
+                    return Err(anyhow!("no such service \"{service}\""));
+                }
+                for service in services.iter() {
+                    service.signal(signal)?;
+                }
+                serde_json::to_writer(stream, &ActionResult::Ok(()))?;
+            }
+            Action::Reload { service } => {
+                let services = Server::find_services(monitor, &service);
+                if services.is_empty() {
+                    return Err(anyhow!("no such service \"{service}\""));
+                }
+                for service in services.iter() {
+                    monitor.reload(service)?;
+                }
                 serde_json::to_writer(stream, &ActionResult::Ok(()))?;
             }
             Action::Stop { service } => {
-                let service = Server::find_service(monitor, &service)
-                    .with_context(|| format!("no such service \"{service}\""))?;
-
-                monitor.stop(&service);
+                let services = Server::find_services(monitor, &service);
+                if services.is_empty() {
+                    return Err(anyhow!("no such service \"{service}\""));
+                }
+                for service in services.iter() {
+                    monitor.stop(service);
+                }
                 serde_json::to_writer(stream, &ActionResult::Ok(()))?;
             }
             Action::ShowConfiguration => {
@@ -236,12 +291,48 @@ impl Server {
             Action::ShowScheduler => {
                 serde_json::to_writer(stream, &ActionResult::Ok(monitor.scheduler.dump()))?;
             }
+            Action::SchedulerState => {
+                serde_json::to_writer(stream, &ActionResult::Ok(monitor.scheduler.state()))?;
+            }
+            Action::PauseScheduler { service } => {
+                match service {
+                    Some(service) => {
+                        let services = Server::find_services(monitor, &service);
+                        if services.is_empty() {
+                            return Err(anyhow!("no such service \"{service}\""));
+                        }
+                        for service in services.iter() {
+                            monitor.pause_scheduler(Some(service));
+                        }
+                    }
+                    None => monitor.pause_scheduler(None),
+                }
+                serde_json::to_writer(stream, &ActionResult::Ok(()))?;
+            }
+            Action::ResumeScheduler { service } => {
+                match service {
+                    Some(service) => {
+                        let services = Server::find_services(monitor, &service);
+                        if services.is_empty() {
+                            return Err(anyhow!("no such service \"{service}\""));
+                        }
+                        for service in services.iter() {
+                            monitor.resume_scheduler(Some(service));
+                        }
+                    }
+                    None => monitor.resume_scheduler(None),
+                }
+                serde_json::to_writer(stream, &ActionResult::Ok(()))?;
+            }
             Action::Add {
                 name,
                 env,
                 command,
                 schedule,
                 workdir,
+                user,
+                group,
+                supplementary_groups,
             } => {
                 let mut args = command.into_iter();
                 let path = args.next().context("command is empty")?;
@@ -254,18 +345,65 @@ impl Server {
                     service.schedule = Some(schedule.parse()?);
                 }
                 service.workdir = workdir;
+                service.user = user;
+                service.group = group;
+                service.supplementary_groups = supplementary_groups;
 
-                monitor.insert(service);
+                monitor.insert(service.validate()?);
                 serde_json::to_writer(stream, &ActionResult::Ok(()))?;
             }
             Action::Remove { service } => {
+                let services = Server::find_services(monitor, &service);
+                if services.is_empty() {
+                    return Err(anyhow!("no such service \"{service}\""));
+                }
+                monitor.check_removal(&services)?;
+                for service in services.iter() {
+                    monitor.stop(service);
+                    monitor.remove(&service.id);
+                }
+                serde_json::to_writer(stream, &ActionResult::Ok(()))?;
+            }
+            Action::Scale { service, instances } => {
                 let service = Server::find_service(monitor, &service)
                     .with_context(|| format!("no such service \"{service}\""))?;
-                service.stop();
-                monitor.remove(&service.id);
+
+                monitor.scale(&service, instances)?;
                 serde_json::to_writer(stream, &ActionResult::Ok(()))?;
             }
-            Action::ListLogFiles { service } => {
+            Action::ListLogFiles {
+                service,
+                run: Some(run),
+            } => {
+                let service = Server::find_service(monitor, &service)
+                    .with_context(|| format!("no such service \"{service}\""))?;
+                let logger = monitor
+                    .logger
+                    .as_ref()
+                    .filter(|logger| logger.per_run)
+                    .context("logger `per_run` mode is required")?;
+                /* runs are counted from the files on disk, history is only used to find crashes */
+                let log_file = match run {
+                    RunSelector::Last => logger.run_files(&service.name).pop(),
+                    RunSelector::Nth(n) => {
+                        logger.run_files(&service.name).into_iter().rev().nth(n - 1)
+                    }
+                    RunSelector::Failed => service
+                        .info()
+                        .history
+                        .iter()
+                        .rev()
+                        .find(|run| run.status == Status::Crashed)
+                        .context("no such run")?
+                        .log_file
+                        .clone()
+                        .filter(|file| file.exists()),
+                }
+                .context("no log file for this run")?;
+
+                serde_json::to_writer(stream, &ActionResult::Ok([log_file]))?;
+            }
+            Action::ListLogFiles { service, run: None } => {
                 let logger = monitor
                     .logger
                     .as_ref()
@@ -315,6 +453,22 @@ mod tests {
         let client = Client::connect(addr)?;
         client.run(&Action::Info).expect("command failed");
 
+        let worker = Service::new("worker", Command::new("sleep", ["300"]));
+        worker
+            .instances
+            .store(2, std::sync::atomic::Ordering::Relaxed);
+        let mut ids = Vec::new();
+        for srv in worker.expand()? {
+            srv.set_active(false);
+            ids.push(server.monitor.insert(srv).id);
+        }
+        let python = Service::new("python3.11", Command::new("ls", ["-la"]));
+        python.set_active(false);
+        server.monitor.insert(python);
+        let replicas: std::collections::HashMap<ServiceId, (ServiceId, usize)> =
+            client.invoke(&Action::ListReplicas)?;
+        assert_eq!(replicas, [(ids[1], (ids[0], 1))].into());
+
         Ok(())
     }
 }
@@ -29,7 +29,18 @@ use std::{
 
 #[cfg(target_os = "linux")]
 use ppm::utils::signal::{SIGTERM, Signal};
-use ppm::utils::{libc::getpid, signal::SignalSet, tracing_utils::tracing_init};
+use ppm::{
+    monitor::cgroup::CGroup,
+    service::{
+        LAUNCHER_ENV_CGROUP, LAUNCHER_ENV_GROUP, LAUNCHER_ENV_GROUPS, LAUNCHER_ENV_LIMITS,
+        LAUNCHER_ENV_USER, Limits,
+    },
+    utils::{
+        libc::{Credentials, getpid, setsid},
+        signal::SignalSet,
+        tracing_utils::tracing_init,
+    },
+};
 
 /// This is an intermediate binary to properly
 /// restore signals and set a couple flags on the new process.
@@ -50,14 +61,47 @@ fn main() -> Result<()> {
         .restore()
         .expect("failed to restore default signal handlers");
 
+    /* own session and process group, so that the whole tree can be signaled */
+    if let Err(err) = setsid() {
+        tracing::error!(?err, "failed to create session");
+    }
+
     #[cfg(target_os = "linux")]
     if let Err(err) = Signal::set_pdeath_sig(SIGTERM) {
         tracing::error!(?err, "failed to set pdeath signal");
     }
 
+    if let Ok(cgroup) = env::var(LAUNCHER_ENV_CGROUP) {
+        CGroup::new(cgroup)
+            .add_process(getpid())
+            .inspect_err(|err| tracing::error!(?err, "failed to join cgroup"))?;
+    }
+
+    /* limits are applied first, raising hard limits requires privileges */
+    if let Ok(limits) = env::var(LAUNCHER_ENV_LIMITS) {
+        serde_json::from_str::<Limits>(&limits)
+            .map_err(anyhow::Error::from)
+            .and_then(|limits| limits.apply())
+            .inspect_err(|err| tracing::error!(?err, "failed to apply limits"))?;
+    }
+
+    let user = env::var(LAUNCHER_ENV_USER).ok();
+    let group = env::var(LAUNCHER_ENV_GROUP).ok();
+    let groups: Vec<String> = env::var(LAUNCHER_ENV_GROUPS)
+        .map(|groups| groups.split(',').map(str::to_owned).collect())
+        .unwrap_or_default();
+    Credentials::resolve(user.as_deref(), group.as_deref(), &groups)
+        .and_then(|creds| creds.apply())
+        .inspect_err(|err| tracing::error!(?err, "failed to drop privileges"))?;
+
     tracing::trace!(bin, "starting process");
     let mut cmd = process::Command::new(bin);
     cmd.args(args)
+        .env_remove(LAUNCHER_ENV_USER)
+        .env_remove(LAUNCHER_ENV_GROUP)
+        .env_remove(LAUNCHER_ENV_GROUPS)
+        .env_remove(LAUNCHER_ENV_LIMITS)
+        .env_remove(LAUNCHER_ENV_CGROUP)
         .stdin(process::Stdio::null())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
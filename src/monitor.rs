@@ -21,22 +21,41 @@
 ** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
 */
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Local};
 use dashmap::DashMap;
+use libc::{WIFEXITED, WIFSIGNALED, WTERMSIG, c_int, pid_t};
 use serde::{Deserialize, Serialize};
 use std::{
-    sync::{Arc, Mutex},
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    process,
+    sync::{
+        Arc, Mutex,
+        atomic::Ordering,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
     time::{Duration, Instant},
 };
 
 use crate::{
-    monitor::{logger::Logger, scheduler::SchedulerEvent, watcher::WatcherTrait},
-    service::{Info, Service, ServiceId, Stats, Status},
+    monitor::{
+        logger::Logger,
+        scheduler::{PausePolicy, SchedulerEvent, is_default_pause_policy},
+        watcher::WatcherTrait,
+    },
+    service::{
+        CatchUp, ConcurrencyPolicy, Health, Info, OnChange, RunTrigger, Schedule, Service,
+        ServiceId, Stats, Status,
+    },
     utils::{
         self,
-        libc::{getpgid, getpid, gettid, setsid, waitpid},
-        serializers::{human, instant::RefTimePoint},
-        signal::{SIGALRM, SIGCHLD, SIGHUP, SIGINT, SIGTERM, Signal, SignalSet, Timer},
+        libc::{getpgid, getpid, gettid, setsid, try_waitpid, waitpid, waitpid_peek},
+        serializers::{
+            human,
+            instant::{RefTimePoint, to_systime},
+        },
+        signal::{SIGALRM, SIGCHLD, SIGHUP, SIGINT, SIGKILL, SIGTERM, Signal, SignalSet, Timer},
     },
 };
 
@@ -46,6 +65,14 @@ use crate::utils::libc::set_child_subreaper;
 mod sysinfo;
 use sysinfo::Sysinfo;
 
+pub mod cgroup;
+use cgroup::CGroup;
+
+pub mod dependencies;
+
+mod state;
+use state::State;
+
 pub mod scheduler;
 use scheduler::Scheduler;
 
@@ -54,6 +81,9 @@ use watcher::Watcher;
 
 pub mod logger;
 
+/// Maximum number of pending runs, caught up at once or queued
+pub const CATCH_UP_MAX_RUNS: usize = 100;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Monitor {
@@ -69,9 +99,20 @@ pub struct Monitor {
     pub services: DashMap<ServiceId, Arc<Service>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logger: Option<Logger>,
+    /// Delegated cgroup v2 directory, services are spawned in sub-groups
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cgroup: Option<PathBuf>,
+    /// File persisting the daemon state (ex: last scheduled runs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_file: Option<PathBuf>,
+    /// Fate of the scheduled runs and watch restarts while the scheduler is paused
+    #[serde(skip_serializing_if = "is_default_pause_policy")]
+    pub pause_policy: PausePolicy,
     #[serde(skip)]
     pub scheduler: Scheduler,
     #[serde(skip)]
+    state: Mutex<State>,
+    #[serde(skip)]
     watcher: Mutex<Option<Watcher>>,
     #[serde(skip)]
     sysinfo: Mutex<Sysinfo>,
@@ -81,6 +122,12 @@ pub struct Monitor {
     start_time: Instant,
     #[serde(skip)]
     tid: Mutex<Option<libc::pthread_t>>,
+    /// Helper processes (not services) waiting for their exit status
+    #[serde(skip)]
+    tasks: Mutex<HashMap<pid_t, Sender<c_int>>>,
+    /// Services running a watch `on_change` command
+    #[serde(skip)]
+    watch_tasks: Mutex<HashSet<ServiceId>>,
 }
 
 impl Default for Monitor {
@@ -92,12 +139,18 @@ impl Default for Monitor {
             clock_check_interval: Duration::from_hours(1),
             services: Default::default(),
             logger: Default::default(),
+            cgroup: None,
+            state_file: None,
+            pause_policy: Default::default(),
             scheduler: Default::default(),
+            state: Default::default(),
             watcher: Default::default(),
             sysinfo: Default::default(),
             _stats: Default::default(),
             start_time: Instant::now(),
             tid: Default::default(),
+            tasks: Default::default(),
+            watch_tasks: Default::default(),
         }
     }
 }
@@ -128,26 +181,79 @@ impl Monitor {
         Ok(())
     }
 
-    pub fn on_sigchld(&self) -> usize {
+    pub fn on_sigchld(self: &Arc<Self>) -> usize {
         self.waitpid(-1)
     }
 
     #[tracing::instrument(level = "TRACE", skip(self), ret)]
-    fn waitpid(&self, pid: libc::pid_t) -> usize {
+    fn waitpid(self: &Arc<Self>, pid: libc::pid_t) -> usize {
         let mut count = 0;
-        while let Some((pid, status)) = waitpid(pid, false) {
+        while let Some(child) = waitpid_peek(pid) {
+            /* process group must be retrieved before releasing the zombie */
+            let pgid = getpgid(child);
+            let (pid, status) = match try_waitpid(child, false) {
+                Ok(Some(ret)) => ret,
+                /* reaped by another thread (ex: Service::terminate), others may be pending */
+                Err(err) if err.raw_os_error() == Some(libc::ECHILD) => continue,
+                _ => break,
+            };
             count += 1;
             if let Some(service) = self.find_by_pid(pid) {
-                let is_crashed = matches!(service.set_terminated(pid, status), Status::Crashed);
+                let state = service.set_terminated(pid, status);
+                if state == Status::Crashed
+                    && WIFSIGNALED(status)
+                    && WTERMSIG(status) == *SIGKILL
+                    && let Some(cgroup) = service.info().cgroup.as_ref()
+                {
+                    service.set_oom_kills(CGroup::new(cgroup).oom_kills().unwrap_or_default());
+                }
+                if state == Status::Finished && !WIFSIGNALED(status) {
+                    self.set_last_run(&service);
+                }
+                self.schedule_after_run(&service);
+                let is_crashed = matches!(state, Status::Crashed);
                 let throttle = service.update_throttle(self.uptime_slices(&service), is_crashed);
-                if is_crashed {
-                    self.scheduler.enqueue(SchedulerEvent::ServiceRestart {
-                        id: service.id,
-                        instant: self.next_restart(&service.info(), throttle),
+                let stopped = service.info().stopping;
+                let active = service.info().active;
+                let restart = (active
+                    && service.restart.should_restart(state, stopped)
+                    && service.register_restart())
+                .then(|| SchedulerEvent::ServiceRestart {
+                    id: service.id,
+                    instant: self.next_restart(&service.info(), throttle),
+                    immediate: false,
+                });
+                /* inactive services are being stopped, `post_stop` is run by the caller */
+                if service.post_stop.is_some()
+                    && active
+                    && matches!(state, Status::Finished | Status::Crashed)
+                {
+                    /* restart once the hook completed */
+                    let monitor = Arc::clone(self);
+                    std::thread::spawn(move || {
+                        monitor.post_stop(&service);
+                        if let Some(event) = restart
+                            && monitor.scheduler.enqueue(event)
+                        {
+                            monitor.wake();
+                        }
                     });
+                } else if let Some(event) = restart {
+                    self.scheduler.enqueue(event);
+                }
+            } else if WIFEXITED(status) || WIFSIGNALED(status) {
+                match self.tasks.lock().unwrap().remove(&pid) {
+                    Some(tx) => tx.send(status).unwrap_or_default(),
+                    None => match self.find_by_pgid(pgid) {
+                        Some(service) => tracing::debug!(
+                            pid,
+                            id = service.id,
+                            name = service.name,
+                            "orphan process terminated"
+                        ),
+                        None => tracing::warn!(pid, "unknown process"),
+                    },
                 }
-            } else {
-                tracing::warn!(pid, "unknown process");
             }
         }
         count
@@ -170,34 +276,165 @@ impl Monitor {
     #[tracing::instrument(skip(self))]
     pub fn process(self: &Arc<Self>) {
         for event in self.scheduler.iter() {
+            if let SchedulerEvent::ServiceSchedule { id, date_time, .. } = &event
+                && let Some(service) = self.get(id)
+            {
+                self.set_last_attempt(&service, *date_time);
+            }
+            if self.scheduler.is_paused(&event) {
+                self.on_paused(event);
+                continue;
+            }
+            if let Some((service, until)) = self.blackout(&event) {
+                tracing::info!(id = service.id, %until, "blackout window, deferring event");
+                self.scheduler.defer(&service, event, until);
+                continue;
+            }
             match event {
-                SchedulerEvent::ServiceSchedule { id, date_time, .. } => {
+                SchedulerEvent::ServiceSchedule {
+                    id,
+                    date_time,
+                    skipped,
+                    ..
+                } => {
                     if let Some(service) = self.get(&id)
                         && service.info().active
                     {
-                        service.restart(self.logger.as_ref());
-                        self.scheduler.reschedule(&service, Some(date_time));
+                        let running = service.info().pid.is_some();
+                        let ready = self.is_ready(&service);
+                        let mut overlap = None;
+                        if !ready {
+                            tracing::warn!(id, "dependencies not running, skipping run");
+                        } else if !running {
+                            service.set_trigger(RunTrigger::Schedule);
+                            self.spawn(&service);
+                        } else {
+                            match service.concurrency_policy {
+                                ConcurrencyPolicy::Replace => {
+                                    tracing::info!(id, "previous run still running, replacing it");
+                                    service.set_trigger(RunTrigger::Schedule);
+                                    self.spawn(&service);
+                                }
+                                ConcurrencyPolicy::Skip => {
+                                    tracing::warn!(id, "previous run still running, skipping run");
+                                    self.scheduler.reschedule_skipped(
+                                        &service,
+                                        date_time,
+                                        skipped + 1,
+                                    );
+                                    continue;
+                                }
+                                ConcurrencyPolicy::Queue => {
+                                    tracing::info!(id, "previous run still running, queuing run");
+                                    self.scheduler.enqueue_runs(
+                                        id,
+                                        Instant::now() + self.restart_interval,
+                                        1,
+                                    );
+                                }
+                            }
+                            overlap = Some(service.concurrency_policy);
+                        }
+                        /* `from: completion` runs are rescheduled on termination */
+                        if !ready
+                            || !service
+                                .schedule
+                                .as_ref()
+                                .is_some_and(Schedule::is_from_completion)
+                        {
+                            self.scheduler.reschedule_overlapped(
+                                &service,
+                                Some(date_time),
+                                overlap,
+                            );
+                        }
                     } else {
                         tracing::warn!(id, "unknown service");
                     }
                 }
+                SchedulerEvent::ServiceQueued { id, runs, .. } => {
+                    if let Some(service) = self.get(&id)
+                        && service.info().active
+                    {
+                        let runs = if service.info().pid.is_some() || !self.is_ready(&service) {
+                            runs
+                        } else {
+                            service.set_trigger(RunTrigger::Schedule);
+                            self.spawn(&service);
+                            runs - 1
+                        };
+                        if runs != 0 {
+                            self.scheduler.enqueue(SchedulerEvent::ServiceQueued {
+                                id,
+                                instant: Instant::now() + self.restart_interval,
+                                runs,
+                            });
+                        }
+                    }
+                }
+                SchedulerEvent::ServiceTimeout { id, pid, .. } => {
+                    if let Some(service) = self.get(&id)
+                        && service.info().pid == Some(pid)
+                    {
+                        tracing::warn!(id, name = service.name, timeout = ?service.timeout,
+                            "scheduled run timed out, stopping");
+                        let monitor = Arc::clone(self);
+                        std::thread::spawn(move || monitor.stop_run(&service));
+                    }
+                }
                 SchedulerEvent::ServiceRestart { id, .. }
                 | SchedulerEvent::WatchServiceRestart { id, .. } => {
                     if let Some(service) = self.get(&id) {
-                        if service.info().active {
+                        if !service.info().active {
+                            tracing::warn!(id, "not restarting inactive service");
+                        } else if matches!(event, SchedulerEvent::WatchServiceRestart { .. })
+                            && !self.on_change(&service)
+                        {
+                            tracing::debug!(id, "change handled without restart");
+                        } else if !self.is_ready(&service) {
+                            tracing::debug!(id, "waiting for dependencies");
+                            self.scheduler.enqueue(SchedulerEvent::ServiceRestart {
+                                id,
+                                instant: Instant::now() + self.restart_interval,
+                                immediate: matches!(
+                                    event,
+                                    SchedulerEvent::ServiceRestart {
+                                        immediate: true,
+                                        ..
+                                    }
+                                ),
+                            });
+                        } else {
+                            if matches!(event, SchedulerEvent::WatchServiceRestart { .. }) {
+                                service.set_trigger(RunTrigger::Watch);
+                            }
                             self.remove_watch(&service.id);
-                            service.restart(self.logger.as_ref());
+                            self.spawn(&service);
                             self.add_watch(&service)
                                 .unwrap_or_else(|err| tracing::error!(?err, "watcher failure"));
-                        } else {
-                            tracing::warn!(id, "not restarting inactive service");
                         }
                     } else {
                         tracing::warn!(id, "unknown service");
                     }
                 }
+                SchedulerEvent::ServiceStart { id, .. } => {
+                    if let Some(service) = self.get(&id)
+                        && service.info().active
+                    {
+                        self.start(&service);
+                    }
+                }
+                SchedulerEvent::HealthCheck { id, .. } => {
+                    if let Some(service) = self.get(&id)
+                        && let Some(pid) = service.info().pid
+                    {
+                        let monitor = Arc::clone(self);
+                        std::thread::spawn(move || monitor.health_check(&service, pid));
+                    }
+                }
                 SchedulerEvent::Sysinfo { instant } => {
                     self.sysinfo.lock().unwrap().update(self);
+                    self.check_thresholds();
                     self.scheduler.enqueue(SchedulerEvent::Sysinfo {
                         instant: instant + self.stats_interval,
                     });
@@ -205,7 +442,7 @@ impl Monitor {
                 SchedulerEvent::ClockCheck { instant } => {
                     if !RefTimePoint::check(None) {
                         tracing::info!("refreshing scheduler");
-                        self.scheduler.init(self);
+                        self.refresh_schedules();
                     }
                     self.scheduler.enqueue(SchedulerEvent::ClockCheck {
                         instant: instant + self.clock_check_interval,
@@ -215,6 +452,71 @@ impl Monitor {
         }
     }
 
+    /// Hold or drop an event suspended by a scheduler pause
+    #[tracing::instrument(skip(self))]
+    fn on_paused(&self, event: SchedulerEvent) {
+        let event = match event {
+            SchedulerEvent::ServiceSchedule {
+                id,
+                instant,
+                date_time,
+                ..
+            } => {
+                /* keep the schedule going, the run is held as a queued run */
+                if let Some(service) = self.get(&id)
+                    && service.info().active
+                {
+                    self.scheduler.reschedule(&service, Some(date_time));
+                }
+                SchedulerEvent::ServiceQueued {
+                    id,
+                    instant,
+                    runs: 1,
+                }
+            }
+            event => event,
+        };
+        match self.pause_policy {
+            PausePolicy::Hold => {
+                tracing::info!(id = event.id(), "scheduler paused, holding event");
+                self.scheduler.hold(event);
+            }
+            PausePolicy::Drop => {
+                tracing::info!(id = event.id(), "scheduler paused, dropping event")
+            }
+        }
+    }
+
+    /// End of the blackout window a deferrable event falls in
+    ///
+    /// Manual restarts and the initial start are never deferred.
+    fn blackout(&self, event: &SchedulerEvent) -> Option<(Arc<Service>, DateTime<Local>)> {
+        if !matches!(
+            event,
+            SchedulerEvent::ServiceSchedule { .. }
+                | SchedulerEvent::ServiceQueued { .. }
+                | SchedulerEvent::ServiceRestart { .. }
+                | SchedulerEvent::WatchServiceRestart { .. }
+        ) {
+            return None;
+        }
+        let service = self.get(&event.id()?)?;
+        if service.blackout.is_empty()
+            || !service.info().active
+            || matches!(
+                event,
+                SchedulerEvent::ServiceRestart {
+                    immediate: true,
+                    ..
+                }
+            )
+        {
+            return None;
+        }
+        let until = service.blackout_end(Local::now())?;
+        Some((service, until))
+    }
+
     pub fn run(self: &Arc<Self>) -> Result<()> {
         let _span = tracing::info_span!(parent: None, "monitor").entered();
         *self.tid.lock().unwrap() = Some(gettid());
@@ -228,9 +530,33 @@ impl Monitor {
             sigset.restore().unwrap();
         });
 
+        if let Some(cgroup) = self.cgroup.as_ref()
+            && let Err(err) = CGroup::init_root(cgroup)
+        {
+            /* limits can't be applied without controllers */
+            if self
+                .services
+                .iter()
+                .any(|service| !service.cgroup.is_empty())
+            {
+                return Err(err.context("failed to initialize cgroup"));
+            }
+            tracing::error!(?err, "failed to initialize cgroup");
+        }
+
+        if let Some(state_file) = self.state_file.as_ref() {
+            match State::load(state_file) {
+                Ok(state) => *self.state.lock().unwrap() = state,
+                Err(err) => tracing::error!(?err, "failed to load state"),
+            }
+        }
+        self.restore_pause_state();
+
         self.scheduler.init(self);
-        for srv in self.services.iter().filter(|srv| srv.info().active) {
-            self.inject(&srv);
+        for srv in self.levels().iter().flatten() {
+            if srv.info().active {
+                self.inject(srv);
+            }
         }
 
         let mut timer = Timer::new(Duration::from_millis(1), false);
@@ -253,7 +579,7 @@ impl Monitor {
                 }
                 SIGHUP => {
                     tracing::info!("refreshing scheduler");
-                    self.scheduler.init(self);
+                    self.refresh_schedules();
                 }
                 SIGCHLD => {
                     self.on_sigchld();
@@ -261,17 +587,7 @@ impl Monitor {
                 signal @ (SIGTERM | SIGINT) => {
                     tracing::info!("termination requested ({:?})", signal);
                     // timer.stop()?;
-                    for service in self.services.iter() {
-                        if let Some(pid) = service.info().pid {
-                            tracing::trace!(
-                                id = service.id,
-                                name = service.name,
-                                pid,
-                                "stopping service"
-                            );
-                            let _ = Signal::kill(pid, SIGTERM);
-                        }
-                    }
+                    self.shutdown();
                     return Ok(());
                 }
                 signal => {
@@ -282,6 +598,452 @@ impl Monitor {
         }
     }
 
+    /// (Re)start a service and arm its health check
+    ///
+    /// Services with hooks are started from a dedicated thread.
+    fn spawn(self: &Arc<Self>, service: &Arc<Service>) {
+        if let Some(cgroup) = self.cgroup.as_ref() {
+            match CGroup::new(cgroup)
+                .service(&service.name)
+                .and_then(|cgroup| {
+                    cgroup.apply(&service.cgroup)?;
+                    Ok(cgroup)
+                }) {
+                Ok(cgroup) => {
+                    let oom_kills = cgroup.oom_kills().unwrap_or_default();
+                    service.set_cgroup(Some(cgroup.path), oom_kills);
+                }
+                Err(err) => {
+                    tracing::error!(?err, id = service.id, name = service.name, "cgroup error");
+                    service.set_cgroup(None, 0);
+                }
+            }
+        } else if !service.cgroup.is_empty() {
+            tracing::warn!(
+                id = service.id,
+                name = service.name,
+                "cgroup limits ignored, daemon cgroup not configured"
+            );
+        }
+        if service.pre_start.is_some()
+            || (service.post_stop.is_some() && service.info().pid.is_some())
+        {
+            let monitor = Arc::clone(self);
+            let service = Arc::clone(service);
+            std::thread::spawn(move || monitor.prepare_start(&service));
+        } else {
+            self.start(service);
+        }
+    }
+
+    /// Start a service, arm its health check and run its `post_start` hook
+    ///
+    /// Must be called from the [Monitor] thread, processes are terminated
+    /// with their parent thread.
+    fn start(self: &Arc<Self>, service: &Arc<Service>) {
+        service.restart(self.logger.as_ref());
+        if let Some(healthcheck) = service.healthcheck.as_ref() {
+            self.scheduler.enqueue(SchedulerEvent::HealthCheck {
+                id: service.id,
+                instant: Instant::now() + healthcheck.interval,
+            });
+        }
+        if service.schedule.is_some()
+            && let Some(timeout) = service.timeout
+            && let Some(pid) = service.info().pid
+        {
+            self.scheduler.enqueue(SchedulerEvent::ServiceTimeout {
+                id: service.id,
+                pid,
+                instant: Instant::now() + timeout,
+            });
+        }
+        if service.info().pid.is_none() {
+            self.schedule_after_run(service);
+        }
+        if service.info().pid.is_some() && service.post_start.is_some() {
+            let monitor = Arc::clone(self);
+            let service = Arc::clone(service);
+            std::thread::spawn(move || {
+                if let Some(hook) = service.post_start.as_ref()
+                    && let Err(err) = hook.run(&service, &monitor)
+                {
+                    tracing::warn!(
+                        ?err,
+                        id = service.id,
+                        name = service.name,
+                        "post_start hook failed"
+                    );
+                }
+            });
+        }
+    }
+
+    /// Recompute schedules (ex: after a clock jump), catching up missed runs
+    fn refresh_schedules(self: &Arc<Self>) {
+        for service in self.services.iter() {
+            if service.schedule.is_some() && service.info().active {
+                self.catch_up(&service);
+                self.scheduler.reschedule(&service, None);
+            }
+        }
+    }
+
+    /// Record a successful scheduled run
+    fn set_last_run(&self, service: &Service) {
+        let start_time = match (service.schedule.as_ref(), service.info().start_time) {
+            (Some(_), Some(start_time)) => start_time,
+            _ => return,
+        };
+        let mut state = self.state.lock().unwrap();
+        state
+            .last_runs
+            .insert(service.name.clone(), to_systime(&start_time).into());
+        self.save_state(&state);
+    }
+
+    /// Record a dispatched scheduled occurrence
+    fn set_last_attempt(&self, service: &Service, date_time: DateTime<Local>) {
+        let mut state = self.state.lock().unwrap();
+        state.last_attempts.insert(service.name.clone(), date_time);
+        self.save_state(&state);
+    }
+
+    /// Persist the scheduler pause state
+    fn save_pause_state(&self) {
+        let sched = self.scheduler.state();
+        let paused_services = sched
+            .paused_services
+            .iter()
+            .filter_map(|id| self.get(id).map(|srv| srv.name.clone()))
+            .collect();
+        let mut state = self.state.lock().unwrap();
+        state.paused = sched.paused;
+        state.paused_services = paused_services;
+        self.save_state(&state);
+    }
+
+    /// Pause the scheduler as it was when the daemon stopped
+    fn restore_pause_state(&self) {
+        let (paused, paused_services) = {
+            let state = self.state.lock().unwrap();
+            (state.paused, state.paused_services.clone())
+        };
+        if paused {
+            self.scheduler.pause(None);
+        }
+        for name in paused_services.iter() {
+            match self.services.iter().find(|srv| &srv.name == name) {
+                Some(srv) => self.scheduler.pause(Some(srv.id)),
+                None => tracing::warn!(name, "unknown paused service"),
+            }
+        }
+    }
+
+    fn save_state(&self, state: &State) {
+        if let Some(state_file) = self.state_file.as_ref()
+            && let Err(err) = state.save(state_file)
+        {
+            tracing::error!(?err, "failed to save state");
+        }
+    }
+
+    /// Queue the scheduled runs missed since the last successful run
+    ///
+    /// - occurrences up to the last dispatched one are not missed
+    /// - nothing is queued while a run is in progress
+    ///
+    /// Returns true if the main thread should be awaken.
+    fn catch_up(&self, service: &Service) -> bool {
+        let schedule = match (service.catch_up, service.schedule.as_ref()) {
+            (CatchUp::None, _) | (_, None) => return false,
+            _ if service.info().pid.is_some() => return false,
+            (_, Some(schedule)) => schedule,
+        };
+        let last_run = {
+            let state = self.state.lock().unwrap();
+            match (
+                state.last_runs.get(&service.name),
+                state.last_attempts.get(&service.name),
+            ) {
+                (Some(last_run), Some(last_attempt)) => *last_run.max(last_attempt),
+                (Some(last_run), None) => *last_run,
+                (None, _) => return false,
+            }
+        };
+        let now = Local::now();
+        let mut last = match service.max_lateness {
+            Some(max_lateness) => last_run.max(now - max_lateness),
+            None => last_run,
+        };
+
+        let mut runs = 0;
+        while runs < CATCH_UP_MAX_RUNS
+            && let Ok(Some(next)) = schedule.find_next_occurrence(&last, false)
+            && next <= now
+        {
+            runs += 1;
+            last = next;
+        }
+        if service.catch_up == CatchUp::Once {
+            runs = runs.min(1);
+        }
+        if runs == 0 {
+            return false;
+        }
+        tracing::info!(
+            id = service.id,
+            name = service.name,
+            %last_run,
+            runs,
+            "catching up missed runs"
+        );
+        self.set_last_attempt(service, last);
+        self.scheduler
+            .enqueue_runs(service.id, Instant::now(), runs)
+    }
+
+    /// Stop a scheduled run, keeping the service active
+    ///
+    /// Runs in a dedicated thread, the process is stopped using the service
+    /// stop sequence.
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
+    fn stop_run(&self, service: &Service) {
+        let active = service.info().active;
+        service.stop();
+        service.set_active(active);
+        self.post_stop(service);
+        if self.schedule_after_run(service) {
+            self.wake();
+        }
+    }
+
+    /// Schedule the next run of an `every` service measured from completion
+    ///
+    /// Returns true if the main thread should be awaken.
+    fn schedule_after_run(&self, service: &Service) -> bool {
+        service.info().active
+            && service
+                .schedule
+                .as_ref()
+                .is_some_and(Schedule::is_from_completion)
+            && self.scheduler.reschedule(service, Some(Local::now()))
+    }
+
+    /// Stop the running process and run the `pre_start` hook
+    ///
+    /// Runs in a dedicated thread, the service is then started by the
+    /// [Monitor] thread or crashed if the `pre_start` hook failed.
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
+    fn prepare_start(&self, service: &Service) {
+        if service.info().pid.is_some() {
+            service.stop();
+            service.set_active(true);
+            self.post_stop(service);
+        }
+
+        let event = match service
+            .pre_start
+            .as_ref()
+            .map(|hook| hook.run(service, self))
+        {
+            Some(Err(err)) => {
+                tracing::error!(?err, "pre_start hook failed");
+                service.set_start_failed();
+                if self.schedule_after_run(service) {
+                    self.wake();
+                }
+                let throttle = service.update_throttle(0, true);
+                if service.restart.should_restart(Status::Crashed, false)
+                    && service.register_restart()
+                {
+                    SchedulerEvent::ServiceRestart {
+                        id: service.id,
+                        instant: self.next_restart(&service.info(), throttle),
+                        immediate: false,
+                    }
+                } else {
+                    return;
+                }
+            }
+            _ => SchedulerEvent::ServiceStart {
+                id: service.id,
+                instant: Instant::now(),
+            },
+        };
+        if service.info().active && self.scheduler.enqueue(event) {
+            self.wake();
+        }
+    }
+
+    /// Run the service `post_stop` hook
+    fn post_stop(&self, service: &Service) {
+        if let Some(hook) = service.post_stop.as_ref()
+            && let Err(err) = hook.run(service, self)
+        {
+            tracing::warn!(
+                ?err,
+                id = service.id,
+                name = service.name,
+                "post_stop hook failed"
+            );
+        }
+    }
+
+    /// Run a service health check
+    ///
+    /// Runs in a dedicated thread, an unhealthy service is restarted using
+    /// the crash restart throttle.
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
+    fn health_check(&self, service: &Service, pid: pid_t) {
+        let healthcheck = match service.healthcheck.as_ref() {
+            Some(healthcheck) => healthcheck,
+            None => return,
+        };
+        let result = healthcheck.check(service, self);
+        if service.info().pid != Some(pid) {
+            tracing::debug!("process restarted, dropping health check result");
+            return;
+        }
+
+        let wake = match service.set_health(result.is_ok()) {
+            Some(Health::Unhealthy) => {
+                tracing::warn!(err = ?result.err(), "service unhealthy, restarting");
+                let throttle = service.update_throttle(self.uptime_slices(service), true);
+                self.scheduler.enqueue(SchedulerEvent::ServiceRestart {
+                    id: service.id,
+                    instant: self.next_restart(&service.info(), throttle),
+                    immediate: false,
+                })
+            }
+            _ => self.scheduler.enqueue(SchedulerEvent::HealthCheck {
+                id: service.id,
+                instant: Instant::now() + healthcheck.interval,
+            }),
+        };
+        if wake {
+            self.wake();
+        }
+    }
+
+    /// Restart services exceeding their `max_memory` or `max_cpu` threshold
+    fn check_thresholds(&self) {
+        for service in self.services.iter() {
+            if !service.info().active
+                || service.info().pid.is_none()
+                || self.scheduler.is_restart_queued(service.id)
+            {
+                continue;
+            }
+            if let Some(reason) = service.check_thresholds() {
+                tracing::warn!(
+                    id = service.id,
+                    name = service.name,
+                    ?reason,
+                    stats = ?service.stats(),
+                    "threshold exceeded, restarting service"
+                );
+                service.set_restart_reason(reason);
+                /* throttled as a crash, a service exceeding its threshold again is restarted later */
+                let throttle = service.update_throttle(self.uptime_slices(&service), true);
+                self.scheduler.enqueue(SchedulerEvent::ServiceRestart {
+                    id: service.id,
+                    instant: self.next_restart(&service.info(), throttle),
+                    immediate: false,
+                });
+            }
+        }
+    }
+
+    /// Spawn a helper process (not a service)
+    ///
+    /// Its exit status is collected by the [Monitor] and sent on the returned
+    /// channel.
+    pub fn spawn_task(&self, cmd: &mut process::Command) -> Result<(pid_t, Receiver<c_int>)> {
+        let (tx, rx) = mpsc::channel();
+        // keep the lock while spawning, [Monitor::waitpid] must not collect
+        // the process before it is registered
+        let mut tasks = self.tasks.lock().unwrap();
+        let pid = cmd.spawn()?.id() as pid_t;
+        tasks.insert(pid, tx);
+        Ok((pid, rx))
+    }
+
+    /// Wait for a helper process started with [Monitor::spawn_task]
+    ///
+    /// The process is also collected from the calling thread, for when the
+    /// [Monitor] is not running (ex: on shutdown). Returns `None` on timeout.
+    pub fn wait_task(&self, pid: pid_t, rx: &Receiver<c_int>, timeout: Duration) -> Option<c_int> {
+        let start = Instant::now();
+        loop {
+            match rx.recv_timeout(Duration::from_millis(10)) {
+                Ok(status) => return Some(status),
+                Err(RecvTimeoutError::Disconnected) => return None,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            {
+                let mut tasks = self.tasks.lock().unwrap();
+                if let Some((_, status)) = waitpid(pid, false)
+                    && (WIFEXITED(status) || WIFSIGNALED(status))
+                {
+                    tasks.remove(&pid);
+                    return Some(status);
+                }
+            }
+            if start.elapsed() >= timeout {
+                return None;
+            }
+        }
+    }
+
+    /// Stop running services, dependent services first
+    fn shutdown(&self) {
+        for level in self.levels().iter().rev() {
+            std::thread::scope(|scope| {
+                for service in level.iter().filter(|srv| srv.info().pid.is_some()) {
+                    tracing::trace!(id = service.id, name = service.name, "stopping service");
+                    scope.spawn(|| {
+                        service.stop();
+                        self.post_stop(service);
+                    });
+                }
+            });
+        }
+    }
+
+    /// Services sorted in dependency levels (see [dependencies::levels])
+    fn levels(&self) -> Vec<Vec<Arc<Service>>> {
+        let services: Vec<Arc<Service>> = self.services.iter().map(|x| Arc::clone(&x)).collect();
+        match dependencies::levels(&services) {
+            Ok(levels) => levels
+                .into_iter()
+                .map(|level| level.into_iter().map(Arc::clone).collect())
+                .collect(),
+            Err(err) => {
+                tracing::error!(?err, "failed to resolve dependencies");
+                vec![services]
+            }
+        }
+    }
+
+    /// Check whether service dependencies are running (and healthy)
+    ///
+    /// A service with an unknown dependency is never ready.
+    fn is_ready(&self, service: &Service) -> bool {
+        service.depends_on.iter().all(|name| {
+            let instances = self.find_instances(name);
+            if instances.is_empty() {
+                tracing::error!(id = service.id, dependency = name, "unknown dependency");
+                return false;
+            }
+            instances.iter().all(|dep| {
+                let info = dep.info();
+                info.status == Status::Running
+                    && (dep.healthcheck.is_none() || info.health == Some(Health::Healthy))
+            })
+        })
+    }
+
     pub fn find_by_pid(&self, pid: libc::pid_t) -> Option<Arc<Service>> {
         self.services
             .iter()
@@ -289,6 +1051,14 @@ impl Monitor {
             .map(|x| Arc::clone(&x))
     }
 
+    /// Find the service that spawned the given process group
+    pub fn find_by_pgid(&self, pgid: libc::pid_t) -> Option<Arc<Service>> {
+        self.services
+            .iter()
+            .find(|x| x.info().pgid.is_some_and(|x| x == pgid))
+            .map(|x| Arc::clone(&x))
+    }
+
     pub fn find_by_name(&self, name: &String) -> Option<Arc<Service>> {
         self.services
             .iter()
@@ -296,6 +1066,18 @@ impl Monitor {
             .map(|x| Arc::clone(&x))
     }
 
+    /// Find a service by name, with all its replicas, ordered by instance
+    pub fn find_instances(&self, name: &str) -> Vec<Arc<Service>> {
+        let mut ret: Vec<Arc<Service>> = self
+            .services
+            .iter()
+            .filter(|x| x.name == name || x.parent_name() == name)
+            .map(|x| Arc::clone(&x))
+            .collect();
+        ret.sort_by_key(|x| x.instance_id());
+        ret
+    }
+
     pub fn get(&self, id: &ServiceId) -> Option<Arc<Service>> {
         self.services.get(id).map(|x| Arc::clone(&x))
     }
@@ -317,13 +1099,17 @@ impl Monitor {
     /// Returns true if scheduler notifies that main thread should be awaken
     fn inject(self: &Arc<Self>, service: &Arc<Service>) -> bool {
         let (watch, wake) = if service.schedule.is_some() {
-            (true, self.scheduler.reschedule(service, None))
+            let wake = self.catch_up(service);
+            (true, self.scheduler.reschedule(service, None) || wake)
         } else if service.info().status != Status::Running {
+            service.set_trigger(RunTrigger::Start);
+            /* initial start, not deferred by blackout windows */
             (
                 false,
                 self.scheduler.enqueue(SchedulerEvent::ServiceRestart {
                     id: service.id,
                     instant: Instant::now(),
+                    immediate: true,
                 }),
             )
         } else {
@@ -345,9 +1131,11 @@ impl Monitor {
     ///   use [reschedule] to activate but not run.
     pub fn restart(&self, service: &Service) {
         service.set_active(true);
+        service.set_trigger(RunTrigger::Manual);
         let mut wake = self.scheduler.enqueue(SchedulerEvent::ServiceRestart {
             id: service.id,
             instant: Instant::now(),
+            immediate: true,
         });
         if service.schedule.is_some() {
             wake |= self.scheduler.reschedule(service, None);
@@ -363,8 +1151,23 @@ impl Monitor {
         self.remove_watch(&service.id);
         /* don't wake, worst case there'll be a spurious wakeup */
         self.scheduler.remove(&service.id);
+        let running = service.info().pid.is_some();
         service.stop();
         service.update_throttle(self.uptime_slices(service), false);
+        if running {
+            self.post_stop(service);
+        }
+    }
+
+    /// Reload a service, using its `reload_command` or `reload_signal`
+    ///
+    /// Blocks while `reload_command` runs, must not be called from the
+    /// [Monitor] thread.
+    pub fn reload(&self, service: &Service) -> Result<()> {
+        match service.reload_command.as_ref() {
+            Some(hook) => hook.run(service, self),
+            None => service.signal(service.reload_signal),
+        }
     }
 
     /// Reschedule a service
@@ -377,6 +1180,83 @@ impl Monitor {
         }
     }
 
+    /// Pause scheduled runs and watch restarts, globally or for a single service
+    ///
+    /// Running services are not affected.
+    pub fn pause_scheduler(&self, service: Option<&Service>) {
+        self.scheduler.pause(service.map(|s| s.id));
+        self.save_pause_state();
+    }
+
+    /// Resume scheduled runs and watch restarts, globally or for a single service
+    pub fn resume_scheduler(&self, service: Option<&Service>) {
+        let wake = self.scheduler.resume(service.map(|s| s.id));
+        self.save_pause_state();
+        if wake {
+            self.wake();
+        }
+    }
+
+    /// Run the watch `on_change` action of a service
+    ///
+    /// - returns true if the service must be restarted
+    /// - `run` commands are run in a dedicated thread, the service is
+    ///   restarted once it succeeded
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
+    fn on_change(self: &Arc<Self>, service: &Arc<Service>) -> bool {
+        let on_change = match service.watch.as_ref() {
+            Some(watch) => &watch.on_change,
+            None => return true,
+        };
+        match on_change {
+            OnChange::Restart => return true,
+            OnChange::Signal(signal) => {
+                if let Err(err) = service.signal(*signal) {
+                    tracing::warn!(?err, "failed to signal service");
+                }
+            }
+            OnChange::Run(hook) => {
+                if !self.watch_tasks.lock().unwrap().insert(service.id) {
+                    tracing::debug!("previous command still running, postponing");
+                    self.scheduler.enqueue(SchedulerEvent::WatchServiceRestart {
+                        id: service.id,
+                        instant: Instant::now() + self.watch_restart_interval,
+                    });
+                    return false;
+                }
+                let (monitor, service, hook) =
+                    (Arc::clone(self), Arc::clone(service), hook.clone());
+                std::thread::spawn(move || {
+                    let ret = hook.run(&service, &monitor);
+                    monitor.watch_tasks.lock().unwrap().remove(&service.id);
+                    match ret {
+                        Ok(()) => {
+                            service.set_trigger(RunTrigger::Watch);
+                            if monitor.scheduler.enqueue(SchedulerEvent::ServiceRestart {
+                                id: service.id,
+                                instant: Instant::now(),
+                                immediate: false,
+                            }) {
+                                monitor.wake();
+                            }
+                        }
+                        Err(err) => tracing::warn!(
+                            ?err,
+                            id = service.id,
+                            name = service.name,
+                            "on_change command failed, not restarting"
+                        ),
+                    }
+                });
+            }
+        }
+        /* refresh watches, new directories may have been created */
+        self.remove_watch(&service.id);
+        self.add_watch(service)
+            .unwrap_or_else(|err| tracing::error!(?err, "watcher failure"));
+        false
+    }
+
     pub fn on_watch_event(self: &Arc<Self>, service: &Service) {
         if self.scheduler.enqueue(SchedulerEvent::WatchServiceRestart {
             id: service.id,
@@ -419,12 +1299,59 @@ impl Monitor {
         false
     }
 
-    /// Remove service
-    pub fn remove(&self, service_id: &ServiceId) {
-        self.services.remove(service_id);
-        /* don't wake, worst case there'll be a spurious wakeup */
-        self.scheduler.remove(service_id);
-        self.remove_watch(service_id);
+    /// Change the number of instances of a service
+    ///
+    /// Replicas are created from the parent service configuration and
+    /// activated like it, extra replicas are stopped and removed.
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service), err)]
+    pub fn scale(self: &Arc<Self>, service: &Service, instances: usize) -> Result<()> {
+        if service.replica.is_some() {
+            return Err(anyhow!(
+                "`{}` is a replica, scale its parent service",
+                service.name
+            ));
+        } else if instances == 0 {
+            return Err(anyhow!("invalid number of instances"));
+        }
+        let replicas = self.find_instances(&service.name);
+        for replica in replicas.iter().filter(|x| x.instance_id() >= instances) {
+            self.stop(replica);
+            self.remove(&replica.id);
+        }
+        let active = service.info().active;
+        for index in replicas.len()..instances {
+            let replica = service.replica(index)?;
+            replica.set_active(active);
+            self.insert(replica);
+        }
+        service.instances.store(instances, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Check that remaining services don't depend on the removed ones
+    pub fn check_removal(&self, removed: &[Arc<Service>]) -> Result<()> {
+        let services: Vec<Arc<Service>> = self
+            .services
+            .iter()
+            .filter(|x| !removed.iter().any(|srv| srv.id == *x.key()))
+            .map(|x| Arc::clone(&x))
+            .collect();
+        dependencies::levels(&services)
+            .map(|_| ())
+            .context("service is still required")
+    }
+
+    /// Remove service
+    pub fn remove(&self, service_id: &ServiceId) {
+        if let Some((_, service)) = self.services.remove(service_id)
+            && let Some(cgroup) = service.info().cgroup.as_ref()
+            && let Err(err) = CGroup::new(cgroup).remove()
+        {
+            tracing::warn!(?err, ?cgroup, "failed to remove cgroup");
+        }
+        /* don't wake, worst case there'll be a spurious wakeup */
+        self.scheduler.remove(service_id);
+        self.remove_watch(service_id);
     }
 
     /// Retrieve latest stats
@@ -464,15 +1391,19 @@ mod tests {
     use super::*;
 
     use crate::{
-        service::{Command, Status},
+        service::{Command, CpuThreshold, RestartPolicy, RestartReason, Status},
         utils::{
-            kill_on_drop,
-            signal::{SIGALRM, SIGCHLD, SIGCONT, SIGKILL, SIGSTOP, SIGTERM, Signal, SignalSet},
+            MkTemp, kill_on_drop,
+            signal::{
+                SIGALRM, SIGCHLD, SIGCONT, SIGKILL, SIGSTOP, SIGTERM, SIGUSR1, Signal, SignalSet,
+            },
             tracing_utils::tracing_init,
             wait_for,
         },
     };
     use anyhow::Result;
+    use chrono::TimeDelta;
+    use serde_yaml_ng as yaml;
     use serial_test::serial;
 
     #[ctor::ctor]
@@ -521,7 +1452,7 @@ mod tests {
         srv.start(None);
 
         assert_eq!(sigset.wait()?, SIGCHLD);
-        Monitor::default().on_sigchld();
+        Arc::<Monitor>::default().on_sigchld();
 
         sigset.restore()
     }
@@ -581,6 +1512,126 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn history() -> Result<()> {
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let service = mon.insert(Service::new("test_history", Command::new("sleep", ["300"])));
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(service.info().pid.is_some()).expect("not started");
+        mon.restart(&service);
+        wait_for!(service.info().history.len() == 2 && service.info().pid.is_some())
+            .expect("not restarted");
+        Signal::kill(service.info().pid.unwrap(), SIGKILL)?;
+        wait_for!(service.info().history.len() == 3 && service.info().pid.is_some())
+            .expect("not restarted");
+
+        let history = service.info().history.clone();
+        assert_eq!(
+            history.iter().map(|run| run.trigger).collect::<Vec<_>>(),
+            [RunTrigger::Start, RunTrigger::Manual, RunTrigger::Restart]
+        );
+        assert_eq!(history[0].exit.as_ref().unwrap().signal, Some(SIGTERM));
+        assert_eq!(history[1].exit.as_ref().unwrap().signal, Some(SIGKILL));
+        assert_eq!(history[2].exit, None);
+
+        mon.stop(&service);
+        Ok(())
+    }
+
+    /// Inject stats, as collected by [Sysinfo]
+    fn inject_stats(service: &Service, cpu_usage: f32, mem_rss: u64) {
+        service.update_stats(Stats {
+            cpu_usage,
+            mem_rss,
+            uptime: service.info().uptime(),
+            ..Default::default()
+        });
+    }
+
+    /// Queued restart instant of a service
+    fn queued_restart(mon: &Monitor, service: &Service) -> Option<Instant> {
+        mon.scheduler.dump().into_iter().find_map(|evt| match evt {
+            SchedulerEvent::ServiceRestart { id, instant, .. } if id == service.id => Some(instant),
+            _ => None,
+        })
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn thresholds() -> Result<()> {
+        /* monitor is not running, injected stats are not overwritten */
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let mut srv = Service::new("memory", Command::new("sleep", ["300"]));
+        srv.max_memory = Some(1 << 20);
+        let memory = mon.insert(srv);
+        let mut srv = Service::new("cpu", Command::new("sleep", ["300"]));
+        srv.max_cpu = Some(CpuThreshold {
+            usage: 50.0,
+            duration: Duration::from_millis(200),
+        });
+        let cpu = mon.insert(srv);
+        for service in [&memory, &cpu] {
+            mon.scheduler.remove(&service.id);
+            service.restart(None);
+        }
+
+        inject_stats(&memory, 0.0, 1 << 20);
+        inject_stats(&cpu, 40.0, 1 << 30);
+        mon.check_thresholds();
+        assert!(mon.scheduler.dump().is_empty());
+
+        inject_stats(&memory, 0.0, 2 << 20);
+        inject_stats(&cpu, 60.0, 0);
+        mon.check_thresholds();
+        let memory_restart = queued_restart(&mon, &memory).expect("memory not restarted");
+        assert!(memory_restart >= Instant::now() + mon.restart_interval / 2);
+        assert_eq!(memory.info().restart_reason, Some(RestartReason::MaxMemory));
+        assert!(
+            queued_restart(&mon, &cpu).is_none(),
+            "cpu usage must be sustained"
+        );
+
+        std::thread::sleep(Duration::from_millis(200));
+        mon.check_thresholds();
+        assert!(queued_restart(&mon, &cpu).is_some(), "cpu not restarted");
+        assert_eq!(cpu.info().restart_reason, Some(RestartReason::MaxCpu));
+        /* queued restarts are not postponed */
+        assert_eq!(queued_restart(&mon, &memory), Some(memory_restart));
+
+        std::thread::sleep(mon.restart_interval);
+        mon.process();
+        assert_eq!(memory.info().restarts, 2);
+        assert_eq!(cpu.info().restarts, 2);
+        assert_eq!(memory.info().crashed + cpu.info().crashed, 0);
+
+        /* exceeding again doubles the restart delay */
+        inject_stats(&memory, 0.0, 2 << 20);
+        let start = Instant::now();
+        mon.check_thresholds();
+        assert!(
+            queued_restart(&mon, &memory).expect("memory not restarted")
+                >= start + mon.restart_interval * 2
+        );
+
+        for service in [&memory, &cpu] {
+            mon.stop(service);
+        }
+        Ok(())
+    }
+
     #[test]
     #[serial(waitpid)]
     fn restart_throttle() -> Result<()> {
@@ -620,6 +1671,262 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn restart_policy() -> Result<()> {
+        let mon = Arc::new(Monitor {
+            restart_interval: std::time::Duration::from_millis(10),
+            ..Default::default()
+        });
+        let mut srv = Service::new("always", Command::new("true", [""; 0]));
+        srv.restart = RestartPolicy::Always;
+        let always = mon.insert(srv);
+        let mut srv = Service::new("never", Command::new("false", [""; 0]));
+        srv.restart = RestartPolicy::Never;
+        let never = mon.insert(srv);
+        let mut srv = Service::new("limited", Command::new("false", [""; 0]));
+        srv.max_restarts = Some(2);
+        let limited = mon.insert(srv);
+        let mut srv = Service::new("unless_stopped", Command::new("sleep", ["300"]));
+        srv.restart = RestartPolicy::UnlessStopped;
+        srv.success_signals = vec![SIGUSR1];
+        let unless_stopped = mon.insert(srv);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(always.info().restarts > 2).expect("not restarted");
+        wait_for!(limited.info().status == Status::Failed).expect("not failed");
+        assert_eq!(limited.info().restarts, 3);
+        assert!(!limited.info().active);
+        assert_eq!(never.info().status, Status::Crashed);
+        assert_eq!(never.info().restarts, 1);
+
+        /* manual restart recovers from the failed state */
+        mon.restart(&limited);
+        wait_for!(limited.info().restarts > 3).expect("not restarted");
+
+        /* a success signal is not a stop */
+        wait_for!(unless_stopped.info().pid.is_some()).expect("not started");
+        Signal::kill(unless_stopped.info().pid.unwrap(), SIGUSR1)?;
+        wait_for!(unless_stopped.info().restarts == 2).expect("not restarted");
+        assert_eq!(unless_stopped.info().status, Status::Running);
+        mon.stop(&unless_stopped);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn hooks() -> Result<()> {
+        use crate::service::Hook;
+
+        let dir = MkTemp::dir("ppm-hooks")?;
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let hook = |msg: &str| Hook::new(Command::new("sh", ["-c", &format!("echo {msg} >> log")]));
+        let mut srv = Service::new("test_hooks", Command::new("sleep", ["300"]));
+        srv.workdir = Some(dir.to_string_lossy().to_string());
+        srv.pre_start = Some(hook("pre_start"));
+        srv.post_start = Some(hook("post_start"));
+        srv.post_stop = Some(hook("post_stop"));
+        let service = mon.insert(srv);
+
+        let mut srv = Service::new("test_pre_start_failure", Command::new("sleep", ["300"]));
+        srv.pre_start = Some(Hook::new(Command::new("false", [""; 0])));
+        srv.max_restarts = Some(1);
+        let failing = mon.insert(srv);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        let log = dir.join("log");
+        wait_for!(std::fs::read_to_string(&log).is_ok_and(|log| log == "pre_start\npost_start\n"))
+            .expect("hooks not run");
+        assert!(service.info().pid.is_some());
+
+        mon.stop(&service);
+        assert_eq!(
+            std::fs::read_to_string(&log)?,
+            "pre_start\npost_start\npost_stop\n"
+        );
+
+        wait_for!(failing.info().status == Status::Failed).expect("not failed");
+        assert_eq!(failing.info().crashed, 2);
+        assert_eq!(failing.info().restarts, 0);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn reload() -> Result<()> {
+        use crate::service::Hook;
+
+        let dir = MkTemp::dir("ppm-reload")?;
+        let mon = Arc::new(Monitor::default());
+        let mut srv = Service::new(
+            "test_signal",
+            Command::new(
+                "sh",
+                [
+                    "-c",
+                    "trap 'echo hup >> log' HUP; trap 'echo usr1 >> log' USR1; \
+                    touch ready; while true; do sleep 0.1; done",
+                ],
+            ),
+        );
+        srv.workdir = Some(dir.to_string_lossy().to_string());
+        let signaled = mon.insert(srv);
+        let mut srv = Service::new("test_reload", Command::new("sleep", ["300"]));
+        srv.workdir = Some(dir.to_string_lossy().to_string());
+        srv.reload_command = Some(Hook::new(Command::new(
+            "sh",
+            ["-c", "echo reload_command >> log"],
+        )));
+        let reloaded = mon.insert(srv);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(dir.join("ready").exists() && reloaded.info().pid.is_some())
+            .expect("not started");
+        let log = dir.join("log");
+        mon.reload(&signaled)?;
+        wait_for!(std::fs::read_to_string(&log).is_ok_and(|log| log == "hup\n"))
+            .expect("not reloaded");
+        signaled.signal(SIGUSR1)?;
+        wait_for!(std::fs::read_to_string(&log).is_ok_and(|log| log == "hup\nusr1\n"))
+            .expect("not signaled");
+
+        mon.reload(&reloaded)?;
+        assert_eq!(
+            std::fs::read_to_string(&log)?,
+            "hup\nusr1\nreload_command\n"
+        );
+
+        for service in [&signaled, &reloaded] {
+            let info = service.info();
+            assert_eq!(info.status, Status::Running);
+            assert_eq!(info.restarts, 1);
+            assert_eq!(info.throttle, 0);
+        }
+
+        mon.stop(&signaled);
+        signaled
+            .signal(SIGUSR1)
+            .expect_err("service is not running");
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn cgroup() -> Result<()> {
+        /* not an actual cgroup, files are faked */
+        let dir = MkTemp::dir("ppm-cgroup")?;
+        std::fs::write(dir.join("cgroup.controllers"), "memory\n")?;
+        let mon = Arc::new(Monitor {
+            cgroup: Some(dir.to_path_buf()),
+            restart_interval: std::time::Duration::from_secs(60),
+            ..Default::default()
+        });
+        let mut srv = Service::new("test", Command::new("sleep", ["300"]));
+        srv.cgroup.memory_max = Some(1 << 20);
+        let service = mon.insert(srv);
+        let cgroup = dir.join("test");
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(service.info().pid.is_some()).expect("not started");
+        let pid = service.info().pid.unwrap();
+        assert_eq!(service.info().cgroup.as_ref(), Some(&cgroup));
+        assert_eq!(
+            std::fs::read_to_string(cgroup.join("memory.max"))?,
+            "1048576"
+        );
+        wait_for!(
+            std::fs::read_to_string(cgroup.join("cgroup.procs"))
+                .is_ok_and(|p| p == pid.to_string())
+        )
+        .expect("not added to cgroup");
+
+        std::fs::write(cgroup.join("memory.events"), "oom 1\noom_kill 1\n")?;
+        Signal::kill(pid, SIGKILL)?;
+        /* OOM kills are recorded once the process is reaped */
+        wait_for!(service.info().status == Status::Crashed && service.info().oom_killed)
+            .expect("not crashed");
+        assert_eq!(service.info().oom_kills, 1);
+
+        service.stop();
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn scale() -> Result<()> {
+        let dir = MkTemp::dir("ppm-scale")?;
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_secs(60),
+            ..Default::default()
+        });
+        let srv = Service::new(
+            "test_scale",
+            Command::new(
+                "sh",
+                [
+                    "-c",
+                    &format!(
+                        "touch {}/$PPM_INSTANCE_ID; exec sleep 300",
+                        dir.to_string_lossy()
+                    ),
+                ],
+            ),
+        );
+        srv.instances.store(2, Ordering::Relaxed);
+        for srv in srv.expand()? {
+            mon.insert(srv);
+        }
+        let service = mon.find_by_name(&"test_scale".into()).unwrap();
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(dir.join("0").exists() && dir.join("1").exists()).expect("not started");
+        let replica = mon.find_by_name(&"test_scale.1".into()).unwrap();
+        assert_eq!(replica.parent_name(), "test_scale");
+        assert_eq!(mon.find_instances("test_scale").len(), 2);
+
+        mon.scale(&service, 3)?;
+        wait_for!(dir.join("2").exists()).expect("not started");
+        assert_eq!(service.instances(), 3);
+        mon.scale(&replica, 1)
+            .expect_err("replicas can't be scaled");
+
+        mon.scale(&service, 1)?;
+        assert_eq!(mon.find_instances("test_scale").len(), 1);
+        assert!(replica.info().pid.is_none());
+        assert!(service.info().pid.is_some());
+
+        service.stop();
+        Ok(())
+    }
+
     #[test]
     #[serial(waitpid)]
     fn manual_restart() -> Result<()> {
@@ -682,10 +1989,9 @@ mod tests {
     #[test]
     #[serial(waitpid)]
     fn scheduled() -> Result<()> {
-        use croner::Cron;
         let mon = Monitor::new();
         let mut service = Service::new("test_stopped", Command::new("sleep", ["300"]));
-        service.schedule = Some(Cron::from_str("* * * * * *")?);
+        service.schedule = Some(Schedule::from_str("* * * * * *")?);
         let service = mon.insert(service);
         let now = Instant::now();
 
@@ -702,4 +2008,423 @@ mod tests {
         wait_for!(service.info().pid.is_none()).expect("not stopped");
         Ok(())
     }
+
+    #[test]
+    #[serial(waitpid)]
+    fn concurrency_policy() -> Result<()> {
+        let mon = Monitor::new();
+        let make = |name: &str, policy: ConcurrencyPolicy| -> Result<Service> {
+            let mut service = Service::new(name, Command::new("sleep", ["300"]));
+            service.schedule = Some(Schedule::from_str("* * * * * *")?);
+            service.concurrency_policy = policy;
+            Ok(service)
+        };
+        let skip = mon.insert(make("test_skip", ConcurrencyPolicy::Skip)?);
+        let queue = mon.insert(make("test_queue", ConcurrencyPolicy::Queue)?);
+        let timeout = {
+            let mut service = make("test_timeout", ConcurrencyPolicy::Skip)?;
+            service.timeout = Some(Duration::from_millis(200));
+            mon.insert(service)
+        };
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(mon.scheduler.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceSchedule { id, skipped, .. } if id == &skip.id && *skipped >= 2
+        )))
+        .expect("runs not skipped");
+        wait_for!(mon.scheduler.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceQueued { id, runs, .. } if id == &queue.id && *runs >= 2
+        )))
+        .expect("queued runs not accumulated");
+        assert!(mon.scheduler.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceSchedule { id, overlap: Some(ConcurrencyPolicy::Queue), .. }
+                if id == &queue.id
+        )));
+        assert_eq!(skip.info().restarts, 1);
+        assert_eq!(queue.info().restarts, 1);
+
+        wait_for!(timeout.info().restarts >= 2).expect("run not stopped on timeout");
+        assert!(timeout.info().active);
+
+        for service in [skip, queue, timeout] {
+            mon.stop(&service);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn interval_schedules() -> Result<()> {
+        let mon = Monitor::new();
+        let make = |name: &str, schedule: &str| -> Result<Service> {
+            let mut service = Service::new(name, Command::new("sleep", ["0.2"]));
+            service.schedule = Some(yaml::from_str(schedule)?);
+            Ok(service)
+        };
+        let every = mon.insert(make("test_every", "{ every: 100ms, jitter: 10ms }")?);
+        let completion = mon.insert(make(
+            "test_completion",
+            "{ every: 100ms, from: completion }",
+        )?);
+        let at = mon.insert({
+            let at = Local::now() + Duration::from_millis(1500);
+            make("test_at", &format!("{{ at: '{}' }}", at.to_rfc3339()))?
+        });
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(at.info().restarts == 1).expect("one-shot run not started");
+        wait_for!(completion.info().restarts >= 3).expect("runs not started");
+        wait_for!(every.info().restarts >= 3).expect("runs not started");
+        /* the `completion` service is only rescheduled once its run terminated */
+        wait_for!(completion.info().pid.is_some()).expect("not started");
+        assert!(!mon.scheduler.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceSchedule { id, .. } if id == &completion.id
+        )));
+
+        wait_for!(at.info().pid.is_none()).expect("one-shot run not finished");
+        assert!(
+            !mon.scheduler
+                .dump()
+                .iter()
+                .any(|event| event.id() == Some(at.id))
+        );
+        assert_eq!(at.info().restarts, 1);
+
+        for service in [every, completion] {
+            mon.stop(&service);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn pause_scheduler() -> Result<()> {
+        let mon = Monitor::new();
+        let make = |name: &str| -> Result<Service> {
+            let mut service = Service::new(name, Command::new("true", [""; 0]));
+            service.schedule = Some(yaml::from_str("{ every: 100ms }")?);
+            Ok(service)
+        };
+        let first = mon.insert(make("test_first")?);
+        let second = mon.insert(make("test_second")?);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(first.info().restarts >= 1 && second.info().restarts >= 1)
+            .expect("runs not started");
+        mon.pause_scheduler(None);
+        wait_for!(mon.scheduler.state().held.len() == 2).expect("runs not held");
+        let restarts = first.info().restarts;
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(first.info().restarts, restarts);
+        assert!(mon.scheduler.state().paused);
+
+        mon.pause_scheduler(Some(&second));
+        mon.resume_scheduler(None);
+        wait_for!(first.info().restarts > restarts).expect("runs not resumed");
+        let state = mon.scheduler.state();
+        assert_eq!(state.paused_services, [second.id].into());
+        assert_eq!(state.held.len(), 1);
+
+        mon.resume_scheduler(Some(&second));
+        wait_for!(mon.scheduler.state().held.is_empty()).expect("runs not released");
+
+        for service in [first, second] {
+            mon.stop(&service);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn pause_state() -> Result<()> {
+        let dir = MkTemp::dir("ppm-pause-state")?;
+        let state_file = dir.join("state.yml");
+        State {
+            paused: true,
+            paused_services: ["test_paused".into(), "test_unknown".into()].into(),
+            ..Default::default()
+        }
+        .save(&state_file)?;
+        let mon = Arc::new(Monitor {
+            state_file: Some(state_file.clone()),
+            ..Default::default()
+        });
+        let make = |name: &str| -> Result<Service> {
+            let mut service = Service::new(name, Command::new("true", [""; 0]));
+            service.schedule = Some(yaml::from_str("{ every: 100ms }")?);
+            Ok(service)
+        };
+        let paused = mon.insert(make("test_paused")?);
+        let other = mon.insert(make("test_other")?);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        /* pause is restored before the first runs */
+        wait_for!(mon.scheduler.state().held.len() == 2).expect("runs not held");
+        assert_eq!(paused.info().restarts + other.info().restarts, 0);
+        assert_eq!(mon.scheduler.state().paused_services, [paused.id].into());
+
+        mon.resume_scheduler(None);
+        wait_for!(other.info().restarts >= 1).expect("runs not resumed");
+        assert_eq!(paused.info().restarts, 0);
+        let state = State::load(&state_file)?;
+        assert!(!state.paused);
+        assert_eq!(state.paused_services, ["test_paused".to_string()].into());
+
+        mon.resume_scheduler(Some(&paused));
+        assert!(State::load(&state_file)?.paused_services.is_empty());
+
+        for service in [paused, other] {
+            mon.stop(&service);
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn blackout() -> Result<()> {
+        let mon = Monitor::new();
+        let now = Local::now();
+        let time = |secs: i64| (now + TimeDelta::seconds(secs)).format("%H:%M:%S");
+        let mut service = Service::new("test_blackout", Command::new("true", [""; 0]));
+        service.schedule = Some(yaml::from_str("{ every: 100ms }")?);
+        service.blackout = vec![format!("{}-{}", time(-1), time(2)).parse()?];
+        let service = mon.insert(service);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(mon.scheduler.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceQueued { id, .. } if id == &service.id
+        )))
+        .expect("scheduled run not deferred");
+        assert_eq!(service.info().restarts, 0);
+
+        /* manual restarts are not deferred */
+        mon.restart(&service);
+        wait_for!(service.info().restarts == 1).expect("manual restart deferred");
+        wait_for!(service.info().restarts >= 3).expect("runs not resumed");
+        assert!(Local::now() >= now + TimeDelta::seconds(1));
+
+        mon.stop(&service);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn blackout_restart() -> Result<()> {
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let now = Local::now();
+        let time = |secs: i64| (now + TimeDelta::seconds(secs)).format("%H:%M:%S");
+        let mut service = Service::new("test_blackout_restart", Command::new("false", [""; 0]));
+        service.restart = RestartPolicy::Always;
+        service.blackout = vec![format!("{}-{}", time(-1), time(3)).parse()?];
+        let service = mon.insert(service);
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        /* initial start runs, the automatic restart is deferred */
+        wait_for!(mon.scheduler.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceRestart { id, instant, immediate: false }
+                if id == &service.id && instant > &(Instant::now() + Duration::from_secs(1))
+        )))
+        .expect("automatic restart not deferred");
+        assert_eq!(service.info().restarts, 1);
+
+        /* manual restarts are not */
+        mon.restart(&service);
+        wait_for!(service.info().restarts == 2).expect("manual restart deferred");
+        assert!(Local::now() < now + TimeDelta::seconds(3));
+
+        mon.stop(&service);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn catch_up() -> Result<()> {
+        let dir = MkTemp::dir("ppm-catch-up")?;
+        let state_file = dir.join("state.yml");
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(10),
+            state_file: Some(state_file.clone()),
+            ..Default::default()
+        });
+        let make = |name: &str, catch_up: CatchUp| -> Result<Service> {
+            let mut service = Service::new(name, Command::new("true", [""; 0]));
+            service.schedule = Some(Schedule::from_str("0 0 0 1 1 *")?);
+            service.catch_up = catch_up;
+            Ok(service)
+        };
+        let all = mon.insert(make("test_all", CatchUp::All)?);
+        let once = mon.insert(make("test_once", CatchUp::Once)?);
+        let late = {
+            let mut service = make("test_late", CatchUp::All)?;
+            service.max_lateness = Some(Duration::from_hours(24 * 366));
+            mon.insert(service)
+        };
+        let none = mon.insert(make("test_none", CatchUp::None)?);
+        let failing = {
+            let mut service = make("test_failing", CatchUp::All)?;
+            service.command = Command::new("false", [""; 0]);
+            service.restart = RestartPolicy::Never;
+            mon.insert(service)
+        };
+
+        let last_run = Local::now() - Duration::from_hours(24 * 365 * 3);
+        let mut state = State::default();
+        for service in [&all, &once, &late, &none, &failing] {
+            state.last_runs.insert(service.name.clone(), last_run);
+        }
+        state.save(&state_file)?;
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(all.info().restarts == 3).expect("missed runs not caught up");
+        wait_for!(once.info().restarts == 1).expect("missed run not caught up");
+        wait_for!(late.info().restarts == 1).expect("missed run not caught up");
+        wait_for!(none.info().restarts != 0, Duration::from_millis(200))
+            .expect_err("missed runs should not be caught up");
+        assert_eq!(all.info().restarts, 3);
+        assert_eq!(late.info().restarts, 1);
+
+        /* failed runs are not caught up again on refresh */
+        wait_for!(failing.info().restarts == 3 && failing.info().pid.is_none())
+            .expect("missed runs not caught up");
+        mon.refresh_schedules();
+        wait_for!(failing.info().restarts != 3, Duration::from_millis(200))
+            .expect_err("failed runs should not be caught up again");
+
+        wait_for!(once.info().status == Status::Finished).expect("not finished");
+        let state = State::load(&state_file)?;
+        assert!(state.last_runs[&once.name] > last_run);
+        assert_eq!(state.last_runs[&none.name], last_run);
+        assert_eq!(state.last_runs[&failing.name], last_run);
+        assert!(state.last_attempts[&failing.name] > last_run);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn depends_on() -> Result<()> {
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let db = Service::new("test_db", Command::new("sleep", ["300"]));
+        db.set_active(false);
+        let db = mon.insert(db);
+        let worker = {
+            let mut srv = Service::new("test_worker", Command::new("sleep", ["300"]));
+            srv.depends_on = vec![db.name.clone()];
+            mon.insert(srv)
+        };
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(worker.info().pid.is_some(), Duration::from_millis(500))
+            .expect_err("should wait for dependencies");
+        let err = mon
+            .check_removal(std::slice::from_ref(&db))
+            .expect_err("worker depends on db");
+        assert!(
+            format!("{err:#}").contains("unknown dependency `test_db`"),
+            "{err:#}"
+        );
+        mon.check_removal(&[Arc::clone(&db), Arc::clone(&worker)])?;
+
+        let mut orphan = Service::new("test_orphan", Command::new("sleep", ["300"]));
+        orphan.depends_on = vec!["test_unknown".into()];
+        assert!(
+            !mon.is_ready(&orphan),
+            "unknown dependency must not be ready"
+        );
+
+        mon.restart(&db);
+        wait_for!(db.info().pid.is_some()).expect("not started");
+        wait_for!(worker.info().pid.is_some()).expect("not started");
+        assert!(worker.info().start_time > db.info().start_time);
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    fn healthcheck() -> Result<()> {
+        use crate::service::{HealthCheck, Probe};
+
+        let mon = Arc::new(Monitor {
+            restart_interval: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let make = |name: &str, probe: &str| {
+            let mut srv = Service::new(name, Command::new("sleep", ["300"]));
+            srv.healthcheck = Some(HealthCheck {
+                interval: Duration::from_millis(100),
+                retries: 2,
+                ..HealthCheck::new(Probe::Exec(Command::new(probe, [""; 0])))
+            });
+            mon.insert(srv)
+        };
+        let healthy = make("test_healthy", "true");
+        let unhealthy = make("test_unhealthy", "false");
+
+        let join_handle = {
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        wait_for!(
+            healthy.info().health == Some(Health::Healthy),
+            "health: {:?}",
+            healthy.info().health
+        )
+        .expect("should be healthy");
+        wait_for!(unhealthy.info().restarts >= 2).expect("unhealthy service not restarted");
+        assert_eq!(healthy.info().restarts, 1);
+        Ok(())
+    }
 }
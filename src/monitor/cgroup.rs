@@ -0,0 +1,312 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T09:12:40
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Context, Result, anyhow, ensure};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::service::CGroupLimits;
+
+/// Controllers enabled for services sub-groups, when available
+const CONTROLLERS: [&str; 4] = ["cpu", "io", "memory", "pids"];
+const CPU_PERIOD: u64 = 100_000;
+/// Leaf sub-group hosting the processes found in the delegated cgroup
+pub const DAEMON_CGROUP: &str = "ppm-daemon";
+
+/// cgroup v2 directory
+#[derive(Debug, Clone, PartialEq)]
+pub struct CGroup {
+    pub path: PathBuf,
+}
+
+/// Accounting for a cgroup and all its descendants
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CGroupStats {
+    pub cpu_time: Duration,
+    pub memory: u64,
+    pub io_read: u64,
+    pub io_write: u64,
+}
+
+impl CGroup {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Prepare a delegated cgroup subtree to host services
+    ///
+    /// Processes living in the delegated cgroup (the daemon) are moved to the
+    /// [DAEMON_CGROUP] leaf, a cgroup with processes can't enable controllers
+    /// for its sub-groups. Available controllers are then enabled.
+    pub fn init_root<P: Into<PathBuf>>(path: P) -> Result<Self> {
+        let ret = Self::new(path);
+        let controllers = ret
+            .read("cgroup.controllers")
+            .with_context(|| format!("not a cgroup v2 directory: {}", ret.path.display()))?;
+        let procs = ret.read("cgroup.procs").unwrap_or_default();
+        if !procs.trim().is_empty() {
+            let daemon = ret.child(DAEMON_CGROUP)?;
+            for pid in procs.split_whitespace() {
+                daemon
+                    .write("cgroup.procs", pid)
+                    .with_context(|| format!("failed to move process {pid} to {DAEMON_CGROUP}"))?;
+            }
+        }
+        for controller in CONTROLLERS
+            .iter()
+            .filter(|c| controllers.split_whitespace().any(|x| &x == *c))
+        {
+            ret.write("cgroup.subtree_control", &format!("+{controller}"))
+                .with_context(|| format!("failed to enable cgroup controller `{controller}`"))?;
+        }
+        Ok(ret)
+    }
+
+    /// Create (or reuse) a sub-group
+    pub fn child(&self, name: &str) -> Result<Self> {
+        let ret = Self::new(self.path.join(name.replace('/', "_")));
+        fs::create_dir_all(&ret.path)
+            .with_context(|| format!("failed to create cgroup {}", ret.path.display()))?;
+        Ok(ret)
+    }
+
+    /// Create (or reuse) a service sub-group
+    pub fn service(&self, name: &str) -> Result<Self> {
+        ensure!(
+            name != DAEMON_CGROUP,
+            "`{DAEMON_CGROUP}` cgroup is reserved for the daemon"
+        );
+        self.child(name)
+    }
+
+    /// Apply limits, unset limits are reset to `max`
+    pub fn apply(&self, limits: &CGroupLimits) -> Result<()> {
+        for (file, value) in [
+            ("memory.max", limits.memory_max.map(|v| v.to_string())),
+            (
+                "cpu.max",
+                limits
+                    .cpu_max
+                    .map(|cpus| format!("{} {CPU_PERIOD}", (cpus * CPU_PERIOD as f64) as u64)),
+            ),
+            ("pids.max", limits.pids_max.map(|v| v.to_string())),
+        ] {
+            match value {
+                Some(value) => self
+                    .write(file, &value)
+                    .with_context(|| format!("failed to set {file}"))?,
+                None if self.path.join(file).exists() => {
+                    self.write(file, "max").unwrap_or_default()
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Move a process in this cgroup
+    pub fn add_process(&self, pid: libc::pid_t) -> Result<()> {
+        self.write("cgroup.procs", &pid.to_string())
+    }
+
+    pub fn stats(&self) -> Result<CGroupStats> {
+        let mut stats = CGroupStats {
+            cpu_time: Duration::from_micros(
+                self.read_key("cpu.stat", "usage_usec")?.unwrap_or_default(),
+            ),
+            memory: self.read("memory.current")?.trim().parse()?,
+            ..Default::default()
+        };
+        /* io controller may not be available */
+        if let Ok(io) = self.read("io.stat") {
+            for (key, value) in io
+                .split_whitespace()
+                .filter_map(|field| field.split_once('='))
+            {
+                match key {
+                    "rbytes" => stats.io_read += value.parse::<u64>().unwrap_or_default(),
+                    "wbytes" => stats.io_write += value.parse::<u64>().unwrap_or_default(),
+                    _ => {}
+                }
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Number of processes killed by the OOM killer in this cgroup
+    pub fn oom_kills(&self) -> Result<u64> {
+        Ok(self
+            .read_key("memory.events", "oom_kill")?
+            .unwrap_or_default())
+    }
+
+    /// Remove the cgroup, must be empty
+    pub fn remove(&self) -> Result<()> {
+        Ok(fs::remove_dir(&self.path)?)
+    }
+
+    fn read(&self, file: &str) -> Result<String> {
+        Ok(fs::read_to_string(self.path.join(file))?)
+    }
+
+    fn write(&self, file: &str, value: &str) -> Result<()> {
+        Ok(fs::write(self.path.join(file), value)?)
+    }
+
+    /// Read a value from a flat-keyed file
+    fn read_key(&self, file: &str, key: &str) -> Result<Option<u64>> {
+        self.read(file)?
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| {
+                v.trim()
+                    .parse::<u64>()
+                    .map_err(|err| anyhow!("invalid {file} value: {err}"))
+            })
+            .transpose()
+    }
+}
+
+impl AsRef<Path> for CGroup {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MkTemp;
+
+    #[test]
+    fn files() -> Result<()> {
+        let dir = MkTemp::dir("ppm-cgroup")?;
+        fs::write(
+            dir.join("cgroup.controllers"),
+            "cpuset cpu io memory pids\n",
+        )?;
+        let root = CGroup::init_root(dir.as_path())?;
+        assert_eq!(
+            fs::read_to_string(dir.join("cgroup.subtree_control"))?,
+            "+pids"
+        );
+
+        let cgroup = root.child("my/service")?;
+        assert_eq!(cgroup.path, dir.join("my_service"));
+        fs::write(cgroup.path.join("pids.max"), "12")?;
+        cgroup.apply(&CGroupLimits {
+            memory_max: Some(1 << 20),
+            cpu_max: Some(0.5),
+            pids_max: None,
+        })?;
+        assert_eq!(
+            fs::read_to_string(cgroup.path.join("memory.max"))?,
+            "1048576"
+        );
+        assert_eq!(
+            fs::read_to_string(cgroup.path.join("cpu.max"))?,
+            "50000 100000"
+        );
+        assert_eq!(fs::read_to_string(cgroup.path.join("pids.max"))?, "max");
+
+        fs::write(
+            cgroup.path.join("cpu.stat"),
+            "usage_usec 1500000\nuser_usec 1000000\n",
+        )?;
+        fs::write(cgroup.path.join("memory.current"), "4096\n")?;
+        fs::write(
+            cgroup.path.join("io.stat"),
+            "8:0 rbytes=100 wbytes=200 rios=1 wios=2\n8:16 rbytes=10 wbytes=20 rios=1 wios=2\n",
+        )?;
+        fs::write(
+            cgroup.path.join("memory.events"),
+            "low 0\nhigh 0\nmax 3\noom 1\noom_kill 1\n",
+        )?;
+        assert_eq!(
+            cgroup.stats()?,
+            CGroupStats {
+                cpu_time: Duration::from_millis(1500),
+                memory: 4096,
+                io_read: 110,
+                io_write: 220,
+            }
+        );
+        assert_eq!(cgroup.oom_kills()?, 1);
+
+        CGroup::init_root(dir.join("my_service")).expect_err("not a cgroup root");
+        Ok(())
+    }
+
+    /// Runs against a real delegated cgroup v2 when the test process lives in
+    /// a writable one
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn delegated() -> Result<()> {
+        let mount = fs::read_to_string("/proc/self/mountinfo")?
+            .lines()
+            .find(|line| line.contains(" - cgroup2 "))
+            .and_then(|line| line.split(' ').nth(4).map(PathBuf::from));
+        let Some(parent) = fs::read_to_string("/proc/self/cgroup")?
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .zip(mount)
+            .map(|(path, mount)| mount.join(path.trim_start_matches('/')))
+            .filter(|path| path.join("cgroup.controllers").exists())
+        else {
+            tracing::warn!("no cgroup v2, skipping test");
+            return Ok(());
+        };
+        let root = CGroup::new(parent.join(format!("ppm-test-{}", std::process::id())));
+        if fs::create_dir(&root.path).is_err() {
+            tracing::warn!("cgroup not delegated, skipping test");
+            return Ok(());
+        }
+        let mut child = std::process::Command::new("sleep").arg("30").spawn()?;
+        let pid = child.id().to_string();
+        let ret = (|| -> Result<()> {
+            root.write("cgroup.procs", &pid)?;
+            let root = CGroup::init_root(root.path.as_path())?;
+            let daemon = root.child(DAEMON_CGROUP)?;
+            assert_eq!(daemon.read("cgroup.procs")?.trim(), pid);
+            if root.read("cgroup.controllers")?.contains("memory") {
+                let service = root.child("service")?;
+                service.apply(&CGroupLimits {
+                    memory_max: Some(1 << 20),
+                    ..Default::default()
+                })?;
+                assert_eq!(service.read("memory.max")?.trim(), "1048576");
+                service.remove()?;
+            }
+            Ok(())
+        })();
+        child.kill()?;
+        child.wait()?;
+        let _ = CGroup::new(root.path.join(DAEMON_CGROUP)).remove();
+        let _ = root.remove();
+        ret
+    }
+}
@@ -0,0 +1,179 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-16T09:12:41
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Result, anyhow};
+use std::{borrow::Borrow, collections::HashMap};
+
+use crate::service::Service;
+
+#[derive(Clone, Copy)]
+enum Mark {
+    Visiting,
+    Done(usize),
+}
+
+/// Sort services in dependency levels
+///
+/// - level `0` services have no dependencies, level `n` services only depend
+///   on services from lower levels
+/// - services are ordered by id within a level
+/// - depending on a replicated service depends on all its instances
+/// - returns an error on unknown dependencies or dependency cycles
+pub fn levels<T>(services: &[T]) -> Result<Vec<Vec<&T>>>
+where
+    T: Borrow<Service>,
+{
+    let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, srv) in services.iter().enumerate() {
+        by_name
+            .entry(srv.borrow().parent_name())
+            .or_default()
+            .push(idx);
+    }
+    let mut marks: Vec<Option<Mark>> = vec![None; services.len()];
+    let mut path = Vec::new();
+
+    for idx in 0..services.len() {
+        visit(services, &by_name, &mut marks, &mut path, idx)?;
+    }
+
+    let mut ret: Vec<Vec<&T>> = Vec::new();
+    for (idx, mark) in marks.iter().enumerate() {
+        if let Some(Mark::Done(level)) = mark {
+            if ret.len() <= *level {
+                ret.resize_with(level + 1, Vec::new);
+            }
+            ret[*level].push(&services[idx]);
+        }
+    }
+    for level in ret.iter_mut() {
+        level.sort_by_key(|srv| Borrow::<Service>::borrow(*srv).id);
+    }
+    Ok(ret)
+}
+
+fn visit<T>(
+    services: &[T],
+    by_name: &HashMap<&str, Vec<usize>>,
+    marks: &mut [Option<Mark>],
+    path: &mut Vec<usize>,
+    idx: usize,
+) -> Result<usize>
+where
+    T: Borrow<Service>,
+{
+    match marks[idx] {
+        Some(Mark::Done(level)) => return Ok(level),
+        Some(Mark::Visiting) => {
+            let start = path.iter().position(|i| *i == idx).unwrap_or(0);
+            let cycle: Vec<&str> = path[start..]
+                .iter()
+                .chain([&idx])
+                .map(|i| services[*i].borrow().name.as_str())
+                .collect();
+            return Err(anyhow!("dependency cycle: {}", cycle.join(" -> ")));
+        }
+        None => {}
+    }
+
+    marks[idx] = Some(Mark::Visiting);
+    path.push(idx);
+    let service = services[idx].borrow();
+    let mut level = 0;
+    for dep in service.depends_on.iter() {
+        let instances = by_name
+            .get(dep.as_str())
+            .ok_or_else(|| anyhow!("unknown dependency `{dep}` for service `{}`", service.name))?;
+        for dep_idx in instances.iter() {
+            level = level.max(visit(services, by_name, marks, path, *dep_idx)? + 1);
+        }
+    }
+    path.pop();
+    marks[idx] = Some(Mark::Done(level));
+    Ok(level)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::Command;
+
+    fn make(name: &str, deps: &[&str]) -> Service {
+        let mut srv = Service::new(name, Command::new("ls", [""; 0]));
+        srv.depends_on = deps.iter().map(|d| d.to_string()).collect();
+        srv
+    }
+
+    fn names(levels: Vec<Vec<&Service>>) -> Vec<Vec<&str>> {
+        levels
+            .into_iter()
+            .map(|l| l.into_iter().map(|s| s.name.as_str()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn ordering() -> Result<()> {
+        let services = [
+            make("worker", &["db", "cache"]),
+            make("db", &[]),
+            make("cache", &["db"]),
+            make("standalone", &[]),
+        ];
+        assert_eq!(
+            names(levels(&services)?),
+            vec![vec!["db", "standalone"], vec!["cache"], vec!["worker"]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn replicas() -> Result<()> {
+        let worker = make("worker", &["db"]);
+        worker
+            .instances
+            .store(2, std::sync::atomic::Ordering::Relaxed);
+        let services: Vec<Service> = [make("web", &["worker"]), make("db", &[])]
+            .into_iter()
+            .chain(worker.expand()?)
+            .collect();
+        assert_eq!(
+            names(levels(&services)?),
+            vec![vec!["db"], vec!["worker", "worker.1"], vec!["web"]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn errors() {
+        let services = [make("a", &["b"]), make("b", &["c"]), make("c", &["a"])];
+        let err = levels(&services).expect_err("cycle should be detected");
+        assert!(err.to_string().contains("a -> b -> c -> a"), "{err}");
+
+        let services = [make("a", &["a"])];
+        levels(&services).expect_err("self-dependency should be detected");
+
+        let services = [make("a", &["unknown"])];
+        let err = levels(&services).expect_err("unknown dependency should be detected");
+        assert!(err.to_string().contains("unknown"), "{err}");
+    }
+}
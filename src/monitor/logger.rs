@@ -50,7 +50,9 @@ mod logpump;
 use logpump::LogPump;
 
 mod logfile;
-use logfile::{LOGFILE_MAX_FILES_DEFAULT, LOGFILE_MAX_SIZE_DEFAULT, LogFile};
+use logfile::{
+    LOGFILE_MAX_FILES_DEFAULT, LOGFILE_MAX_RUNS_DEFAULT, LOGFILE_MAX_SIZE_DEFAULT, LogFile,
+};
 
 const LOGGER_DEFAULT_PATH: &str = "/var/log/";
 pub const LOGGER_DAEMON_NAME: &str = "ppm-daemon";
@@ -64,6 +66,10 @@ pub struct Logger {
     pub path: Arc<PathBuf>,
     pub max_files: usize,
     pub max_file_size: u64,
+    /// Scheduled services write each run to its own file
+    pub per_run: bool,
+    /// Number of run files kept per scheduled service
+    pub max_runs: usize,
     logs: LogMap,
     poller: Mutex<PollerWriter>,
     join_handle: Option<JoinHandle<()>>,
@@ -74,7 +80,7 @@ impl Serialize for Logger {
     where
         S: serde::Serializer,
     {
-        let mut map = serializer.serialize_map(Some(3))?;
+        let mut map = serializer.serialize_map(None)?;
 
         if self
             .path
@@ -90,6 +96,12 @@ impl Serialize for Logger {
         if self.max_file_size != LOGFILE_MAX_SIZE_DEFAULT {
             map.serialize_entry("max_file_size", &human::size::Wrapper(&self.max_file_size))?;
         }
+        if self.per_run {
+            map.serialize_entry("per_run", &self.per_run)?;
+        }
+        if self.max_runs != LOGFILE_MAX_RUNS_DEFAULT {
+            map.serialize_entry("max_runs", &self.max_runs)?;
+        }
         map.end()
     }
 }
@@ -101,6 +113,8 @@ pub struct LoggerOptions {
     max_files: usize,
     #[serde(with = "human::size")]
     max_file_size: u64,
+    per_run: bool,
+    max_runs: usize,
 }
 
 impl<T> From<T> for LoggerOptions
@@ -112,6 +126,8 @@ where
             path: value.into(),
             max_files: LOGFILE_MAX_FILES_DEFAULT,
             max_file_size: LOGFILE_MAX_SIZE_DEFAULT,
+            per_run: false,
+            max_runs: LOGFILE_MAX_RUNS_DEFAULT,
         }
     }
 }
@@ -122,6 +138,8 @@ impl Default for LoggerOptions {
             path: LOGGER_DEFAULT_PATH.into(),
             max_files: LOGFILE_MAX_FILES_DEFAULT,
             max_file_size: LOGFILE_MAX_SIZE_DEFAULT,
+            per_run: false,
+            max_runs: LOGFILE_MAX_RUNS_DEFAULT,
         }
     }
 }
@@ -138,6 +156,8 @@ impl Debug for Logger {
             .field("path", &self.path)
             .field("max_files", &self.max_files)
             .field("max_file_size", &self.max_file_size)
+            .field("per_run", &self.per_run)
+            .field("max_runs", &self.max_runs)
             .finish()
     }
 }
@@ -155,6 +175,8 @@ impl Logger {
             poller: Mutex::new(tx),
             max_files: options.max_files,
             max_file_size: options.max_file_size,
+            per_run: options.per_run,
+            max_runs: options.max_runs,
             join_handle: None,
         };
         if let Err(err) = create_dir_all(ret.path.as_ref()) {
@@ -186,17 +208,55 @@ impl Logger {
     where
         S: Into<String>,
     {
+        self.make_pipe_with(id, name, false)
+    }
+
+    /// Create the pipes of a scheduled service run
+    ///
+    /// In `per_run` mode, each run is written to its own file, the previous
+    /// run's pipes are flushed to its file and closed.
+    pub fn make_run_pipe<S>(&self, id: ServiceId, name: S) -> Result<(PipeWriter, PipeWriter)>
+    where
+        S: Into<String>,
+    {
+        self.make_pipe_with(id, name, self.per_run)
+    }
+
+    fn make_pipe_with<S>(
+        &self,
+        id: ServiceId,
+        name: S,
+        per_run: bool,
+    ) -> Result<(PipeWriter, PipeWriter)>
+    where
+        S: Into<String>,
+    {
+        let output = || {
+            if per_run {
+                LogFile::new_per_run(&self.path, name, self.max_runs)
+            } else {
+                LogFile::new_with_limits(&self.path, name, self.max_file_size, self.max_files)
+            }
+        };
         let mut pump = match self.logs.remove(&id) {
-            Some((_, pump)) => pump,
-            None => LogPump::from(LogFile::new_with_limits(
-                &self.path,
-                name,
-                self.max_file_size,
-                self.max_files,
-            )),
+            Some((_, mut pump)) => {
+                /* previous run pipes may still be draining */
+                if per_run || pump.output.per_run != per_run {
+                    pump.close_inputs();
+                }
+                if pump.output.per_run != per_run {
+                    pump.output = output();
+                }
+                pump
+            }
+            None => LogPump::from(output()),
         };
         // ensure log file can be created, don't create the pump otherwise
-        pump.output.rotate()?;
+        if per_run {
+            pump.output.new_run()?;
+        } else {
+            pump.output.rotate()?;
+        }
         pump.make_input().inspect(|_| {
             self.logs.insert(id, pump);
             self.wake();
@@ -207,6 +267,26 @@ impl Logger {
         self.poller.lock().unwrap().wake()
     }
 
+    /// Log file of the current run, in `per_run` mode
+    pub fn run_file(&self, service: ServiceId) -> Option<PathBuf> {
+        self.logs
+            .get(&service)
+            .filter(|log| log.output.per_run)
+            .and_then(|log| log.output.path().cloned())
+    }
+
+    /// Run log files of a service on disk, oldest first, in `per_run` mode
+    pub fn run_files<S>(&self, name: S) -> Vec<PathBuf>
+    where
+        S: Into<String>,
+    {
+        if self.per_run {
+            LogFile::new_per_run(&self.path, name, self.max_runs).list_files()
+        } else {
+            Vec::new()
+        }
+    }
+
     pub fn list_files(&self, service: ServiceId) -> Vec<PathBuf> {
         match self.logs.get(&service) {
             Some(log) => log.output.list_files(),
@@ -336,8 +416,10 @@ mod tests {
         utils::{MkTemp, wait_for},
     };
     use anyhow::Result;
+    use indexmap::IndexMap;
     use serde_yaml_ng as yaml;
     use serial_test::serial;
+    use std::io::Write;
 
     #[test]
     #[serial(waitpid)]
@@ -348,7 +430,7 @@ mod tests {
 
         let mut srv = Service::new("test", Command::new("echo", ["world"]));
         /* silence ppm-launcher logs to see only sub-command bytes */
-        srv.command.env = Some(HashMap::from([("RUST_LOG".into(), "off".into())]));
+        srv.command.env = Some(IndexMap::from([("RUST_LOG".into(), "off".into())]));
 
         srv.restart(&logger);
         wait_for!(
@@ -377,6 +459,78 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn per_run() -> Result<()> {
+        let temp_dir = MkTemp::dir("logger")?;
+        let logger = Logger::new(LoggerOptions {
+            per_run: true,
+            ..LoggerOptions::from(temp_dir.as_ref())
+        });
+
+        let mut srv = Service::new("test", Command::new("echo", ["world"]));
+        srv.command.env = Some(IndexMap::from([("RUST_LOG".into(), "off".into())]));
+        srv.schedule = Some("* * * * * *".parse()?);
+
+        srv.restart(&logger);
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        srv.restart(&logger);
+
+        let files = logger.list_files(srv.id);
+        assert_eq!(files.len(), 2, "files: {files:?}");
+        let runs: Vec<Option<PathBuf>> = srv
+            .info()
+            .history
+            .iter()
+            .map(|run| run.log_file.clone())
+            .collect();
+        assert_eq!(runs, files.iter().cloned().map(Some).collect::<Vec<_>>());
+        assert_eq!(logger.run_files("test"), files);
+        wait_for!(
+            runs.iter()
+                .flatten()
+                .all(|file| file.metadata().is_ok_and(|m| m.len() == 6)),
+            std::time::Duration::from_secs(3)
+        )
+        .expect("each run should be logged to its own file");
+        Ok(())
+    }
+
+    #[test]
+    fn per_run_flush() -> Result<()> {
+        let temp_dir = MkTemp::dir("logger")?;
+        let logger = Logger::new(LoggerOptions {
+            per_run: true,
+            ..LoggerOptions::from(temp_dir.as_ref())
+        });
+
+        let (mut first, _) = logger.make_run_pipe(1, "test")?;
+        first.write_all(b"first\n")?;
+        let first_file = logger.run_file(1).expect("no run file");
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let (mut second, _) = logger.make_run_pipe(1, "test")?;
+        second.write_all(b"second\n")?;
+        let second_file = logger.run_file(1).expect("no run file");
+        assert_ne!(first_file, second_file);
+
+        /* previous run input is closed, once the logger thread is done polling it */
+        wait_for!(
+            first
+                .write_all(b"late\n")
+                .is_err_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe),
+            std::time::Duration::from_secs(3)
+        )
+        .expect("previous run input should be closed");
+        assert_eq!(std::fs::read_to_string(&first_file)?, "first\n");
+        wait_for!(
+            std::fs::read_to_string(&second_file)? == "second\n",
+            std::time::Duration::from_secs(3)
+        )
+        .expect("second run should be logged to its own file");
+        Ok(())
+    }
+
     #[test]
     fn serde() -> Result<()> {
         let logger: Logger = yaml::from_str("{}")?;
@@ -395,6 +549,11 @@ mod tests {
         assert_eq!(30, logger.max_files);
         assert_eq!(1024 * 1024, logger.max_file_size);
 
+        let logger: Logger = yaml::from_str("path: /tmp\nper_run: true\nmax_runs: 5")?;
+        let logger: Logger = yaml::from_str(yaml::to_string(&logger)?.as_str())?;
+        assert!(logger.per_run);
+        assert_eq!(5, logger.max_runs);
+
         let logger: Logger = yaml::from_str("{}")?;
         let logger: Logger = yaml::from_str(yaml::to_string(&logger)?.as_str())?;
         assert_eq!(&PathBuf::from(LOGGER_DEFAULT_PATH), logger.path.as_path());
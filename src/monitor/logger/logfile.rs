@@ -37,17 +37,22 @@ use std::{
 
 pub const LOGFILE_MAX_SIZE_DEFAULT: u64 = 1024 * 1024 * 20;
 pub const LOGFILE_MAX_FILES_DEFAULT: usize = 3;
-/* RFC3339 length + 1 : `-2345-78-01T34:67:90+23:56.log` */
-const LOGFILE_SUFFIX_LEN: usize = 30;
+pub const LOGFILE_MAX_RUNS_DEFAULT: usize = 10;
 
-static LOGFILE_SUFFIX_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"-\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}\+\d{2}:\d{2}.log").unwrap());
+/* RFC3339 stamp, with milliseconds for per-run files: `-2345-78-01T34:67:90.123+23:56.log` */
+static LOGFILE_SUFFIX_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^-\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d{3})?[+-]\d{2}:\d{2}\.log$").unwrap()
+});
 
 pub struct LogFile {
     file: Option<File>,
+    /// Path of the opened file
+    path: Option<PathBuf>,
     written: usize,
     pub max_size: u64,
     pub max_files: usize,
+    /// One file per run (see [LogFile::new_run]), not rotated on size
+    pub per_run: bool,
     log_dir: Arc<PathBuf>,
     log_name: String,
 }
@@ -80,32 +85,55 @@ impl LogFile {
     {
         Self {
             file: None,
+            path: None,
             written: 0,
             max_size,
             max_files,
+            per_run: false,
             log_dir: log_dir.into_arc(),
             log_name: log_name.into(),
         }
     }
 
+    /// Create a per-run [LogFile], keeping `max_runs` files
+    pub fn new_per_run<T, S>(log_dir: T, log_name: S, max_runs: usize) -> Self
+    where
+        T: IntoArc<PathBuf>,
+        S: Into<String>,
+    {
+        Self {
+            per_run: true,
+            ..Self::new_with_limits(log_dir, log_name, LOGFILE_MAX_SIZE_DEFAULT, max_runs)
+        }
+    }
+
     pub fn as_raw_fd(&self) -> Option<RawFd> {
         self.file.as_ref().map(|f| f.as_raw_fd())
     }
 
+    /// Path of the opened file
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// Per-run files are told apart from rotated ones by their milliseconds
     fn is_match(&self, filename: &str) -> bool {
-        if filename.len() == self.log_name.len() + LOGFILE_SUFFIX_LEN {
-            let (pre, suf) = filename.split_at(self.log_name.len());
-            pre == self.log_name && LOGFILE_SUFFIX_RE.is_match(suf)
-        } else {
-            false
-        }
+        filename
+            .strip_prefix(self.log_name.as_str())
+            .and_then(|suf| LOGFILE_SUFFIX_RE.captures(suf))
+            .is_some_and(|caps| caps.get(1).is_some() == self.per_run)
     }
 
     pub fn make_filename(&self) -> String {
+        let format = if self.per_run {
+            SecondsFormat::Millis
+        } else {
+            SecondsFormat::Secs
+        };
         format!(
             "{}-{}.log",
             self.log_name,
-            chrono::Local::now().to_rfc3339_opts(SecondsFormat::Secs, false)
+            chrono::Local::now().to_rfc3339_opts(format, false)
         )
     }
 
@@ -133,17 +161,23 @@ impl LogFile {
         }
     }
 
+    /// Start a new file for a new run
+    pub fn new_run(&mut self) -> Result<()> {
+        self.file = None;
+        self.rotate()
+    }
+
     pub fn rotate(&mut self) -> Result<()> {
-        if self.file.is_some() && self.written < self.max_size as usize {
+        if self.file.is_some() && (self.per_run || self.written < self.max_size as usize) {
             return Ok(());
         }
 
         let files = self.list_files();
-        let file = match files
+        let (path, file) = match files
             .last()
-            .filter(|p| p.metadata().is_ok_and(|m| m.len() < self.max_size))
+            .filter(|p| !self.per_run && p.metadata().is_ok_and(|m| m.len() < self.max_size))
         {
-            Some(file) => {
+            Some(file) => (file.clone(), {
                 tracing::info!(name = self.log_name, ?file, "existing log file found");
                 File::options()
                     .append(true)
@@ -155,7 +189,7 @@ impl LogFile {
                             tracing::error!(?err, "failed to set non-blocking");
                         }
                     })
-            }
+            }),
             None => {
                 for file in files
                     .iter()
@@ -169,7 +203,7 @@ impl LogFile {
 
                 let file = self.log_dir.join(self.make_filename());
 
-                File::options()
+                let ret = File::options()
                     .create(true)
                     .write(true)
                     .truncate(true)
@@ -192,16 +226,19 @@ impl LogFile {
                         f.add_flag(FdFlags::NONBLOCK).unwrap_or_else(|err| {
                             tracing::error!(?err, "failed to set non-blocking")
                         })
-                    })
+                    });
+                (file, ret)
             }
         };
         match file {
             Ok(file) => {
                 self.file = Some(file);
+                self.path = Some(path);
                 Ok(())
             }
             Err(err) => {
                 self.file = None;
+                self.path = None;
                 Err(anyhow!(err))
             }
         }
@@ -249,6 +286,34 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn log_per_run() -> Result<()> {
+        let temp_dir = MkTemp::dir("logger")?;
+        let mut log = LogFile::new_per_run(temp_dir.clone(), "test", 2);
+
+        let mut paths = Vec::new();
+        for _ in 0..3 {
+            log.new_run()?;
+            log.write(b"this is a test\n")?;
+            paths.push(log.path().cloned().expect("no file opened"));
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        /* older runs are removed */
+        assert_eq!(log.list_files(), paths[1..]);
+        /* rotated files are not runs */
+        let mut rotated = LogFile::new(temp_dir.clone(), "test");
+        rotated.write(b"this is a test\n")?;
+        assert_eq!(rotated.list_files().len(), 1);
+        assert_eq!(log.list_files(), paths[1..]);
+        assert_eq!(paths[2].metadata()?.len(), 15);
+
+        /* size based rotation doesn't apply */
+        log.max_size = 10;
+        log.write(b"this is a test\n")?;
+        assert_eq!(log.path(), Some(&paths[2]));
+        Ok(())
+    }
+
     #[test]
     fn log_reopen() -> Result<()> {
         let temp_dir = MkTemp::dir("logger")?;
@@ -140,6 +140,38 @@ impl LogPump {
         }
     }
 
+    /// Flush pending and remaining input data to the current output, then close inputs
+    ///
+    /// Must be called before switching output, so that a previous run's logs
+    /// don't leak into the next file.
+    pub fn close_inputs(&mut self) {
+        let mut buffer = self.buffer.take().unwrap_or_default();
+        self.log_all(&mut buffer);
+        for mut input in std::mem::take(&mut self.input) {
+            loop {
+                match input.read(buffer.raw()) {
+                    Ok(0) => break,
+                    Ok(sz) => self.log_all(buffer.set_range(..sz)),
+                    Err(err) => {
+                        if err.kind() != ErrorKind::WouldBlock {
+                            tracing::error!(?err, "input error");
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn log_all(&mut self, buffer: &mut Buffer) {
+        while !buffer.is_empty() {
+            match self.log(buffer.as_slice()) {
+                0 => break,
+                sz => buffer.consume(sz),
+            };
+        }
+    }
+
     pub fn make_input(&mut self) -> Result<(PipeWriter, PipeWriter)> {
         let (reader_out, writer_out) = pipe().context("failed to create pipe")?;
         reader_out.add_flag(FdFlags::NONBLOCK)?;
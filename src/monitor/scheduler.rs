@@ -23,23 +23,59 @@
 
 use std::{
     cmp::Ordering,
-    collections::BinaryHeap,
-    sync::{Mutex, MutexGuard},
+    collections::{BTreeSet, BinaryHeap},
+    ops::Not,
+    sync::{
+        Mutex, MutexGuard,
+        atomic::{self, AtomicBool},
+    },
     time::{Duration, Instant},
 };
 
 use chrono::{DateTime, DurationRound, Local, TimeDelta};
+use chrono_tz::Tz;
+use libc::pid_t;
 use serde::{Deserialize, Serialize};
 
-use super::Monitor;
+use super::{CATCH_UP_MAX_RUNS, Monitor};
 use crate::{
-    service::{Service, ServiceId},
+    service::{ConcurrencyPolicy, Service, ServiceId},
     utils::serializers::{self, instant::from_systime},
 };
 
 #[derive(Default, Debug)]
 pub struct Scheduler {
     _queue: Mutex<BinaryHeap<SchedulerEvent>>,
+    /// Global pause
+    paused: AtomicBool,
+    /// Services paused individually
+    paused_services: Mutex<BTreeSet<ServiceId>>,
+    /// Events held while paused, released on resume
+    held: Mutex<Vec<SchedulerEvent>>,
+}
+
+/// Fate of the scheduled runs and watch restarts while the scheduler is paused
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PausePolicy {
+    /// run once on resume
+    #[default]
+    Hold,
+    /// drop them
+    Drop,
+}
+
+/// Scheduler pause state, as reported by `ppm show-scheduler` along with the
+/// queued events
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SchedulerState {
+    #[serde(default, skip_serializing_if = "Not::not")]
+    pub paused: bool,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub paused_services: BTreeSet<ServiceId>,
+    /// Events held until the scheduler is resumed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held: Vec<SchedulerEvent>,
 }
 
 #[derive(Eq, Debug, Serialize, Deserialize, Clone)]
@@ -50,17 +86,59 @@ pub enum SchedulerEvent {
         instant: Instant,
         #[serde(skip)]
         date_time: DateTime<Local>,
+        /// Time zone of the service schedule
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<Tz>,
+        /// Consecutive runs skipped by the `skip` concurrency policy
+        #[serde(default, skip_serializing_if = "is_zero")]
+        skipped: usize,
+        /// Concurrency policy applied to the previous run, if it overlapped
+        /// a running one
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        overlap: Option<ConcurrencyPolicy>,
     },
     ServiceRestart {
         id: ServiceId,
         #[serde(with = "serializers::instant")]
         instant: Instant,
+        /// Not deferred by blackout windows
+        ///
+        /// Set on user requests (start, restart) and on the initial start of a
+        /// service (daemon start, service added), automatic restarts are deferred.
+        #[serde(default, skip_serializing_if = "Not::not")]
+        immediate: bool,
     },
     WatchServiceRestart {
         id: ServiceId,
         #[serde(with = "serializers::instant")]
         instant: Instant,
     },
+    /// Start a service once its `pre_start` hook completed
+    ServiceStart {
+        id: ServiceId,
+        #[serde(with = "serializers::instant")]
+        instant: Instant,
+    },
+    /// Scheduled runs waiting for the previous one to terminate
+    ServiceQueued {
+        id: ServiceId,
+        #[serde(with = "serializers::instant")]
+        instant: Instant,
+        /// Number of pending runs
+        runs: usize,
+    },
+    /// Scheduled run `timeout`
+    ServiceTimeout {
+        id: ServiceId,
+        pid: pid_t,
+        #[serde(with = "serializers::instant")]
+        instant: Instant,
+    },
+    HealthCheck {
+        id: ServiceId,
+        #[serde(with = "serializers::instant")]
+        instant: Instant,
+    },
     Sysinfo {
         #[serde(with = "serializers::instant")]
         instant: Instant,
@@ -76,7 +154,11 @@ impl SchedulerEvent {
         match self {
             Self::ServiceSchedule { id, .. }
             | Self::ServiceRestart { id, .. }
-            | Self::WatchServiceRestart { id, .. } => Some(*id),
+            | Self::WatchServiceRestart { id, .. }
+            | Self::ServiceStart { id, .. }
+            | Self::ServiceQueued { id, .. }
+            | Self::ServiceTimeout { id, .. }
+            | Self::HealthCheck { id, .. } => Some(*id),
             _ => None,
         }
     }
@@ -85,7 +167,11 @@ impl SchedulerEvent {
         match self {
             Self::ServiceSchedule { instant, .. }
             | Self::ServiceRestart { instant, .. }
-            | Self::WatchServiceRestart { instant, .. } => instant,
+            | Self::WatchServiceRestart { instant, .. }
+            | Self::ServiceStart { instant, .. }
+            | Self::ServiceQueued { instant, .. }
+            | Self::ServiceTimeout { instant, .. }
+            | Self::HealthCheck { instant, .. } => instant,
 
             Self::Sysinfo { instant } | Self::ClockCheck { instant } => instant,
         }
@@ -102,6 +188,18 @@ impl SchedulerEvent {
             Self::WatchServiceRestart { id, .. } => {
                 matches!(other, Self::WatchServiceRestart { id: other_id, .. } if id == other_id)
             }
+            Self::ServiceStart { id, .. } => {
+                matches!(other, Self::ServiceStart { id: other_id, .. } if id == other_id)
+            }
+            Self::ServiceQueued { id, .. } => {
+                matches!(other, Self::ServiceQueued { id: other_id, .. } if id == other_id)
+            }
+            Self::ServiceTimeout { id, .. } => {
+                matches!(other, Self::ServiceTimeout { id: other_id, .. } if id == other_id)
+            }
+            Self::HealthCheck { id, .. } => {
+                matches!(other, Self::HealthCheck { id: other_id, .. } if id == other_id)
+            }
             Self::Sysinfo { .. } => {
                 matches!(other, Self::Sysinfo { .. })
             }
@@ -110,6 +208,16 @@ impl SchedulerEvent {
             }
         }
     }
+
+    /// Scheduled runs and watch restarts, suspended while paused
+    pub fn is_pausable(&self) -> bool {
+        matches!(
+            self,
+            Self::ServiceSchedule { .. }
+                | Self::ServiceQueued { .. }
+                | Self::WatchServiceRestart { .. }
+        )
+    }
 }
 
 impl Ord for SchedulerEvent {
@@ -147,27 +255,99 @@ impl Scheduler {
     /// (re)schedule a period service
     ///
     /// - returns true if the enqueued item is the most prioritary in the queue
-    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
     pub fn reschedule(&self, service: &Service, last: Option<DateTime<Local>>) -> bool {
+        self.schedule_next(service, last, 0, None)
+    }
+
+    /// (re)schedule a period service, recording the concurrency policy
+    /// applied to an overlapping run
+    pub fn reschedule_overlapped(
+        &self,
+        service: &Service,
+        last: Option<DateTime<Local>>,
+        overlap: Option<ConcurrencyPolicy>,
+    ) -> bool {
+        self.schedule_next(service, last, 0, overlap)
+    }
+
+    /// Schedule the next run of a service whose run was skipped
+    ///
+    /// - `skipped` is the number of consecutive skipped runs
+    pub fn reschedule_skipped(
+        &self,
+        service: &Service,
+        last: DateTime<Local>,
+        skipped: usize,
+    ) -> bool {
+        self.schedule_next(service, Some(last), skipped, Some(ConcurrencyPolicy::Skip))
+    }
+
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
+    fn schedule_next(
+        &self,
+        service: &Service,
+        last: Option<DateTime<Local>>,
+        skipped: usize,
+        overlap: Option<ConcurrencyPolicy>,
+    ) -> bool {
         if let Some(schedule) = service.schedule.as_ref() {
-            if let Ok(next) = schedule.find_next_occurrence(
+            match schedule.find_next_occurrence(
                 &last
                     .unwrap_or_else(|| Local::now().duration_trunc(TimeDelta::seconds(1)).unwrap()),
                 false,
             ) {
-                tracing::info!(?next);
-                return self.enqueue(SchedulerEvent::ServiceSchedule {
-                    id: service.id,
-                    date_time: next,
-                    instant: from_systime(&next.into()),
-                });
-            } else {
-                tracing::error!("failed to get schedule for service");
+                Ok(Some(next)) => {
+                    let jitter = schedule.jitter_delay();
+                    tracing::info!(?next, timezone = ?schedule.timezone, ?jitter);
+                    return self.enqueue(SchedulerEvent::ServiceSchedule {
+                        id: service.id,
+                        date_time: next,
+                        instant: from_systime(&next.into()) + jitter,
+                        timezone: schedule.timezone,
+                        skipped,
+                        overlap,
+                    });
+                }
+                Ok(None) => tracing::info!("no more scheduled runs"),
+                Err(err) => tracing::error!(?err, "failed to get schedule for service"),
             }
         }
         false
     }
 
+    /// Defer an event to the end of a blackout window
+    ///
+    /// - scheduled runs are deferred as queued runs, the schedule goes on
+    /// - returns true if the enqueued item is the most prioritary in the queue
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service))]
+    pub fn defer(&self, service: &Service, event: SchedulerEvent, until: DateTime<Local>) -> bool {
+        let instant = from_systime(&until.into());
+        let event = match event {
+            SchedulerEvent::ServiceSchedule { id, date_time, .. } => {
+                self.reschedule(service, Some(date_time));
+                return self.enqueue_runs(id, instant, 1);
+            }
+            SchedulerEvent::ServiceQueued { id, runs, .. } => {
+                return self.enqueue_runs(id, instant, runs);
+            }
+            SchedulerEvent::ServiceRestart { id, immediate, .. } => {
+                SchedulerEvent::ServiceRestart {
+                    id,
+                    instant,
+                    immediate,
+                }
+            }
+            SchedulerEvent::WatchServiceRestart { id, .. } => {
+                SchedulerEvent::WatchServiceRestart { id, instant }
+            }
+            event => {
+                tracing::error!(?event, "event can't be deferred");
+                return false;
+            }
+        };
+        self.enqueue(event)
+    }
+
     pub fn peek(&self) -> Option<Duration> {
         self.queue()
             .peek()
@@ -188,9 +368,123 @@ impl Scheduler {
         queue.peek().is_some_and(|e| e.instant() == &stamp)
     }
 
+    /// enqueue scheduled runs, adding them to the already queued ones
+    ///
+    /// - the earliest instant is kept, runs are capped to [CATCH_UP_MAX_RUNS]
+    /// - returns true if the enqueued item is the most prioritary in the queue
+    #[tracing::instrument(level = "TRACE", skip(self), ret)]
+    pub fn enqueue_runs(&self, id: ServiceId, instant: Instant, runs: usize) -> bool {
+        let mut queue = self.queue();
+        let (mut instant, mut runs) = (instant, runs);
+        queue.retain(|evt| match evt {
+            SchedulerEvent::ServiceQueued {
+                id: other_id,
+                instant: other_instant,
+                runs: other_runs,
+            } if other_id == &id => {
+                instant = instant.min(*other_instant);
+                runs += other_runs;
+                false
+            }
+            _ => true,
+        });
+        queue.push(SchedulerEvent::ServiceQueued {
+            id,
+            instant,
+            runs: runs.min(CATCH_UP_MAX_RUNS),
+        });
+        queue.peek().is_some_and(|e| e.instant() == &instant)
+    }
+
+    /// Check whether a restart is queued for the given service
+    pub fn is_restart_queued(&self, service: ServiceId) -> bool {
+        self.queue()
+            .iter()
+            .any(|evt| matches!(evt, SchedulerEvent::ServiceRestart { id, .. } if *id == service))
+    }
+
     pub fn remove(&self, service: &ServiceId) {
         self.queue()
             .retain(|evt| evt.id().is_none_or(|id| &id != service));
+        self.held
+            .lock()
+            .unwrap()
+            .retain(|evt| evt.id().is_none_or(|id| &id != service));
+    }
+
+    /// Pause the scheduler, globally or for a single service
+    #[tracing::instrument(skip(self))]
+    pub fn pause(&self, service: Option<ServiceId>) {
+        match service {
+            Some(id) => {
+                self.paused_services.lock().unwrap().insert(id);
+            }
+            None => self.paused.store(true, atomic::Ordering::Relaxed),
+        }
+    }
+
+    /// Resume the scheduler, globally or for a single service
+    ///
+    /// - held events that are no longer paused are enqueued back
+    /// - returns true if a released event is the most prioritary in the queue
+    #[tracing::instrument(skip(self), ret)]
+    pub fn resume(&self, service: Option<ServiceId>) -> bool {
+        match service {
+            Some(id) => {
+                self.paused_services.lock().unwrap().remove(&id);
+            }
+            None => self.paused.store(false, atomic::Ordering::Relaxed),
+        }
+        let released: Vec<SchedulerEvent> = {
+            let mut held = self.held.lock().unwrap();
+            let (paused, released) = held.drain(..).partition(|evt| self.is_paused(evt));
+            *held = paused;
+            released
+        };
+        let mut wake = false;
+        for evt in released {
+            wake |= match evt {
+                SchedulerEvent::ServiceQueued { id, instant, runs } => {
+                    self.enqueue_runs(id, instant, runs)
+                }
+                evt => self.enqueue(evt),
+            };
+        }
+        wake
+    }
+
+    /// Whether the event is suspended by a global or per-service pause
+    pub fn is_paused(&self, event: &SchedulerEvent) -> bool {
+        event.is_pausable()
+            && (self.paused.load(atomic::Ordering::Relaxed)
+                || event
+                    .id()
+                    .is_some_and(|id| self.paused_services.lock().unwrap().contains(&id)))
+    }
+
+    /// Hold an event until the scheduler is resumed
+    ///
+    /// - events from the same source are merged, the first one is kept
+    /// - queued runs add up, capped to [CATCH_UP_MAX_RUNS]
+    #[tracing::instrument(level = "TRACE", skip(self))]
+    pub fn hold(&self, event: SchedulerEvent) {
+        let mut held = self.held.lock().unwrap();
+        match (held.iter_mut().find(|evt| evt.is_source_eq(&event)), event) {
+            (
+                Some(SchedulerEvent::ServiceQueued { runs, .. }),
+                SchedulerEvent::ServiceQueued { runs: more, .. },
+            ) => *runs = (*runs + more).min(CATCH_UP_MAX_RUNS),
+            (Some(_), _) => {}
+            (None, event) => held.push(event),
+        }
+    }
+
+    pub fn state(&self) -> SchedulerState {
+        SchedulerState {
+            paused: self.paused.load(atomic::Ordering::Relaxed),
+            paused_services: self.paused_services.lock().unwrap().clone(),
+            held: self.held.lock().unwrap().clone(),
+        }
     }
 
     #[inline]
@@ -210,6 +504,14 @@ impl Scheduler {
     }
 }
 
+pub(super) fn is_default_pause_policy(policy: &PausePolicy) -> bool {
+    policy == &PausePolicy::default()
+}
+
+fn is_zero(value: &usize) -> bool {
+    value == &0
+}
+
 pub struct SchedulerIterator<'a>(&'a Scheduler, Instant);
 
 impl Iterator for SchedulerIterator<'_> {
@@ -239,6 +541,7 @@ mod tests {
         sched.enqueue(SchedulerEvent::ServiceRestart {
             instant: Instant::now() + Duration::from_mins(1),
             id: 42,
+            immediate: false,
         });
         sched.enqueue(SchedulerEvent::Sysinfo {
             instant: Instant::now() - Duration::from_mins(1),
@@ -248,6 +551,7 @@ mod tests {
         sched.enqueue(SchedulerEvent::ServiceRestart {
             instant: Instant::now() + Duration::from_mins(1),
             id: 43,
+            immediate: false,
         });
 
         let expired: Vec<SchedulerEvent> = sched.iter().collect();
@@ -258,4 +562,78 @@ mod tests {
         };
         Ok(())
     }
+
+    #[test]
+    fn pause() {
+        let sched = Scheduler::default();
+        let queued = |id| SchedulerEvent::ServiceQueued {
+            id,
+            instant: Instant::now() - Duration::from_secs(1),
+            runs: 1,
+        };
+        let restart = SchedulerEvent::ServiceRestart {
+            id: 1,
+            instant: Instant::now(),
+            immediate: false,
+        };
+
+        sched.pause(None);
+        sched.pause(Some(2));
+        assert!(sched.is_paused(&queued(1)));
+        assert!(!sched.is_paused(&restart), "restarts are never paused");
+
+        sched.hold(queued(1));
+        sched.hold(queued(1));
+        sched.hold(queued(2));
+        assert_eq!(sched.state().held.len(), 2);
+        assert!(matches!(
+            sched.state().held.first(),
+            Some(SchedulerEvent::ServiceQueued { id: 1, runs: 2, .. })
+        ));
+
+        assert!(sched.resume(None));
+        let state = sched.state();
+        assert!(!state.paused);
+        assert_eq!(state.paused_services, BTreeSet::from([2]));
+        assert_eq!(state.held.first().and_then(SchedulerEvent::id), Some(2));
+        assert!(matches!(
+            sched.dump().first(),
+            Some(SchedulerEvent::ServiceQueued { id: 1, runs: 2, .. })
+        ));
+
+        assert!(!sched.is_paused(&queued(1)));
+        assert!(sched.is_paused(&queued(2)));
+        sched.resume(Some(2));
+        assert!(sched.state().held.is_empty());
+        assert_eq!(sched.dump().len(), 2);
+    }
+
+    #[test]
+    fn enqueue_runs() {
+        let sched = Scheduler::default();
+        let now = Instant::now();
+
+        sched.enqueue_runs(1, now + Duration::from_secs(10), 5);
+        assert!(sched.enqueue_runs(1, now + Duration::from_secs(1), 1));
+        sched.enqueue_runs(2, now, 1);
+        let events = sched.dump();
+        assert_eq!(events.len(), 2);
+        match events.last() {
+            Some(SchedulerEvent::ServiceQueued {
+                id: 1,
+                instant,
+                runs,
+            }) => {
+                assert_eq!(instant, &(now + Duration::from_secs(1)));
+                assert_eq!(runs, &6);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        sched.enqueue_runs(1, now, CATCH_UP_MAX_RUNS);
+        assert!(sched.dump().iter().any(|event| matches!(
+            event,
+            SchedulerEvent::ServiceQueued { id: 1, runs, .. } if runs == &CATCH_UP_MAX_RUNS
+        )));
+    }
 }
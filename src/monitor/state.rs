@@ -0,0 +1,88 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T13:52:30
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Not,
+    path::Path,
+};
+
+use crate::utils::{LoadFromFile, SaveToFile};
+
+/// Daemon state, persisted across restarts in the `state_file`
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    /// Last successful run of scheduled services, by service name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub last_runs: BTreeMap<String, DateTime<Local>>,
+    /// Last dispatched occurrence of scheduled services, by service name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub last_attempts: BTreeMap<String, DateTime<Local>>,
+    /// Scheduler paused for all services
+    #[serde(default, skip_serializing_if = "Not::not")]
+    pub paused: bool,
+    /// Services with a paused scheduler, by service name
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub paused_services: BTreeSet<String>,
+}
+
+impl State {
+    /// Load the state, a missing file is an empty state
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.exists() {
+            Self::load_from_file(path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        self.save_to_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MkTemp;
+    use chrono::DurationRound;
+
+    #[test]
+    fn load_save() -> Result<()> {
+        let dir = MkTemp::dir("ppm-state")?;
+        let path = dir.join("state.yml");
+        assert_eq!(State::load(&path)?, State::default());
+
+        let mut state = State::default();
+        let now = Local::now().duration_trunc(chrono::TimeDelta::seconds(1))?;
+        state.last_runs.insert("backup".into(), now);
+        state.last_attempts.insert("backup".into(), now);
+        state.paused_services.insert("backup".into());
+        state.save(&path)?;
+        assert_eq!(State::load(&path)?, state);
+        Ok(())
+    }
+}
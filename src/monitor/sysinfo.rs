@@ -33,7 +33,7 @@ use crate::{
     utils::libc::getpid,
 };
 
-use super::Monitor;
+use super::{Monitor, cgroup::CGroup};
 
 pub struct Sysinfo {
     system: System,
@@ -68,13 +68,24 @@ impl Sysinfo {
 
         if let Some(proc) = self.system.process(Pid::from(getpid() as usize)) {
             let mut stats = monitor._stats.lock().unwrap();
-            *stats = Arc::new(self.make_stats(proc, &stats, Some(monitor.start_time.elapsed())));
+            *stats =
+                Arc::new(self.make_stats(proc, &stats, Some(monitor.start_time.elapsed()), None));
         } else {
             tracing::warn!("failed to update daemon stats");
         }
     }
 
-    fn make_stats(&self, proc: &Process, old: &Stats, uptime: Option<Duration>) -> Stats {
+    /// Build process stats
+    ///
+    /// When a `cgroup` is given, CPU, memory and I/O are read from it, covering
+    /// all descendant processes.
+    fn make_stats(
+        &self,
+        proc: &Process,
+        old: &Stats,
+        uptime: Option<Duration>,
+        cgroup: Option<&CGroup>,
+    ) -> Stats {
         let disk_usage = proc.disk_usage();
         let mut stats = Stats {
             cpu_usage: proc.cpu_usage(),
@@ -87,17 +98,36 @@ impl Sysinfo {
             ..Default::default()
         };
 
+        let cgroup_stats = cgroup.and_then(|cgroup| {
+            cgroup
+                .stats()
+                .inspect_err(|err| tracing::warn!(?err, ?cgroup, "failed to read cgroup stats"))
+                .ok()
+        });
+        if let Some(cgroup_stats) = cgroup_stats.as_ref() {
+            stats.cpu_time = cgroup_stats.cpu_time;
+            stats.mem_rss = cgroup_stats.memory;
+            stats.total_io_read = cgroup_stats.io_read;
+            stats.total_io_write = cgroup_stats.io_write;
+        }
+
         if let Some(interval) = stats.uptime.and_then(|new_uptime| {
             old.uptime
                 .map(|old_uptime| new_uptime.saturating_sub(old_uptime))
         }) && !interval.is_zero()
         {
             let interval = interval.as_secs_f64();
-            stats.io_read = ((disk_usage.total_read_bytes - old.total_io_read) as f64 / interval)
+            stats.io_read = (stats.total_io_read.saturating_sub(old.total_io_read) as f64
+                / interval)
                 .round() as u64;
-            stats.io_write = ((disk_usage.total_written_bytes - old.total_io_write) as f64
+            stats.io_write = (stats.total_io_write.saturating_sub(old.total_io_write) as f64
                 / interval)
                 .round() as u64;
+            if cgroup_stats.is_some() {
+                stats.cpu_usage = (stats.cpu_time.saturating_sub(old.cpu_time).as_secs_f64()
+                    * 100.0
+                    / interval) as f32;
+            }
         }
 
         stats
@@ -132,6 +162,7 @@ impl Sysinfo {
                     proc,
                     &srv.stats(),
                     info.start_time.map(|t| t.elapsed()),
+                    info.cgroup.as_ref().map(CGroup::new).as_ref(),
                 ));
             } else {
                 let stats = srv.stats();
@@ -59,7 +59,7 @@ mod tests {
     };
 
     use crate::{
-        service::{Command, Service},
+        service::{Command, RunTrigger, Service},
         utils::{MkTemp, kill_on_drop, wait_for},
     };
     use serde_yaml_ng as yaml;
@@ -161,6 +161,76 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn on_change() -> Result<()> {
+        let temp = MkTemp::dir("ppm-watch-on-change")?;
+        let out = MkTemp::dir("ppm-watch-on-change-out")?;
+        let (marker, flag) = (out.as_ref().join("marker"), out.as_ref().join("flag"));
+
+        let mon = Arc::new(Monitor {
+            watch_restart_interval: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let make = |name: &str, command: Command, on_change: String| -> Result<Arc<Service>> {
+            let mut srv = Service::new(name, command);
+            srv.watch = Some(yaml::from_str(&format!(
+                "{{ paths: '{temp}', on_change: {on_change} }}"
+            ))?);
+            Ok(mon.insert(srv))
+        };
+        let signal = make(
+            "test_signal",
+            Command::new(
+                "sh",
+                [
+                    "-c",
+                    &format!(
+                        "trap 'echo >> {}' WINCH; while true; do sleep 0.1; done",
+                        marker.display()
+                    ),
+                ],
+            ),
+            /* ignored by `sleep` */
+            "{ signal: SIGWINCH }".into(),
+        )?;
+        let run = make(
+            "test_run",
+            Command::new("sleep", ["300"]),
+            format!(
+                "{{ run: {{ path: test, args: [-e, '{}'] }} }}",
+                flag.display()
+            ),
+        )?;
+
+        let join_handle = {
+            /* Monitor is handling dead processes */
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+        for service in [&signal, &run] {
+            wait_for!(service.info().pid.is_some()).expect("not started");
+            wait_for!(mon.has_watch(&service.id)).expect("failed to set watch");
+        }
+
+        File::create(temp.as_ref().join("first"))?;
+        wait_for!(marker.exists()).expect("signal not sent");
+        wait_for!(run.info().restarts != 1, Duration::from_secs(1))
+            .expect_err("failed command should not restart the service");
+        assert_eq!(signal.info().restarts, 1);
+
+        File::create(&flag)?;
+        File::create(temp.as_ref().join("second"))?;
+        wait_for!(run.info().restarts == 2).expect("service not restarted");
+        assert_eq!(
+            run.info().history.back().map(|run| run.trigger),
+            Some(RunTrigger::Watch)
+        );
+        assert_eq!(signal.info().restarts, 1);
+        Ok(())
+    }
+
     fn make_path<S, I, K>(dir: S, iter: I) -> PathBuf
     where
         S: AsRef<PathBuf>,
@@ -22,8 +22,11 @@
 */
 
 use anyhow::{Result, anyhow};
-use croner::Cron;
-use libc::{WEXITSTATUS, WIFCONTINUED, WIFEXITED, WIFSIGNALED, WIFSTOPPED, WTERMSIG, c_int, pid_t};
+use chrono::{DateTime, Local};
+use libc::{
+    WCOREDUMP, WEXITSTATUS, WIFCONTINUED, WIFEXITED, WIFSIGNALED, WIFSTOPPED, WTERMSIG, c_int,
+    pid_t,
+};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use std::{
@@ -38,14 +41,61 @@ use std::{
 };
 
 use crate::monitor::logger::Logger;
-use crate::utils::libc::waitpid;
-use crate::utils::signal::{self, SIGTERM, Signal};
+use crate::utils::libc::{Credentials, getpgid, waitpid};
+use crate::utils::{
+    serializers::human,
+    signal::{SIGTERM, Signal},
+};
+
+mod blackout;
+pub use blackout::Blackout;
+use blackout::blackout_end;
+
+mod catch_up;
+pub use catch_up::CatchUp;
+use catch_up::is_default_catch_up;
+
+mod cgroup;
+pub use cgroup::CGroupLimits;
+
+mod history;
+pub use history::{Run, RunTrigger};
 
 mod command;
 pub use command::Command;
 
+mod concurrency;
+pub use concurrency::ConcurrencyPolicy;
+use concurrency::is_default_concurrency_policy;
+
+mod environment;
+pub use environment::Environment;
+
+mod healthcheck;
+pub use healthcheck::{Health, HealthCheck, Probe};
+
+mod hook;
+pub use hook::Hook;
+
 mod info;
-pub use info::Info;
+pub use info::{Exit, Info};
+
+mod limits;
+pub use limits::{LIMIT_UNLIMITED, Limit, Limits, Resource};
+
+mod reload;
+use reload::{default_reload_signal, is_default_reload_signal};
+
+mod replica;
+pub use replica::Replica;
+use replica::{default_instances, is_single_instance};
+
+mod restart;
+pub use restart::RestartPolicy;
+use restart::{default_restart_window, is_default_restart_policy, is_default_restart_window};
+
+mod schedule;
+pub use schedule::Schedule;
 
 mod stats;
 pub use stats::Stats;
@@ -53,16 +103,36 @@ pub use stats::Stats;
 mod status;
 pub use status::Status;
 
+mod stop;
+pub use stop::StopStep;
+use stop::{
+    default_stop_escalation, default_stop_signal, default_stop_timeout, is_default_stop_escalation,
+    is_default_stop_signal, is_default_stop_timeout,
+};
+
 mod tabled;
 
+mod threshold;
+pub use threshold::{CpuThreshold, RestartReason};
+
 mod watch;
-pub use watch::Watch;
+pub use watch::{OnChange, Watch};
 
 static S_ID: AtomicUsize = AtomicUsize::new(0);
 pub const SERVICE_ID_INVALID: usize = usize::MAX;
 
 pub type ServiceId = usize;
 
+/// `ppm-launcher` environment variables, removed before `exec`
+pub const LAUNCHER_ENV_USER: &str = "PPM_LAUNCHER_USER";
+pub const LAUNCHER_ENV_GROUP: &str = "PPM_LAUNCHER_GROUP";
+pub const LAUNCHER_ENV_GROUPS: &str = "PPM_LAUNCHER_GROUPS";
+pub const LAUNCHER_ENV_LIMITS: &str = "PPM_LAUNCHER_LIMITS";
+pub const LAUNCHER_ENV_CGROUP: &str = "PPM_LAUNCHER_CGROUP";
+
+/// Instance index exported to the service processes
+pub const ENV_INSTANCE_ID: &str = "PPM_INSTANCE_ID";
+
 static LAUNCHER_EXE: LazyLock<Option<PathBuf>> = LazyLock::new(|| {
     match current_exe()
         .ok()
@@ -117,10 +187,133 @@ pub struct Service {
     pub workdir: Option<String>,
     /// Command schedule for periodic commands
     #[serde(skip_serializing_if = "Option::is_none", default)]
-    pub schedule: Option<Cron>,
+    pub schedule: Option<Schedule>,
+    /// Policy applied when a scheduled run is due while the previous one is running
+    #[serde(skip_serializing_if = "is_default_concurrency_policy", default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+    /// Maximum duration of a scheduled run, the process is stopped once reached
+    #[serde(
+        with = "human::duration",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub timeout: Option<Duration>,
+    /// Scheduled runs to catch up when missed (daemon downtime, clock jumps)
+    #[serde(skip_serializing_if = "is_default_catch_up", default)]
+    pub catch_up: CatchUp,
+    /// Missed runs older than this are not caught up
+    #[serde(
+        with = "human::duration",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub max_lateness: Option<Duration>,
+    /// Windows during which automatic restarts and scheduled runs are deferred
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub blackout: Vec<Blackout>,
+    /// Number of instances to run (see [Service::replica])
+    #[serde(
+        skip_serializing_if = "is_single_instance",
+        default = "default_instances"
+    )]
+    pub instances: AtomicUsize,
+    /// Start the service when the daemon boots, otherwise wait for `ppm start`
+    #[serde(skip_serializing_if = "is_autostart", default = "default_autostart")]
+    pub autostart: bool,
+    /// Replica informations, `None` for the parent service (instance `0`)
+    #[serde(skip, default)]
+    pub replica: Option<Replica>,
     /// Directory watchs to monitor
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub watch: Option<Watch>,
+    /// User to run the service as (name or uid)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub user: Option<String>,
+    /// Group to run the service as (name or gid), defaults to the user's primary group
+    /// (the daemon's group for a numeric uid without passwd entry)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub group: Option<String>,
+    /// Supplementary groups (names or gids)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub supplementary_groups: Vec<String>,
+    /// Resource limits (see `setrlimit(2)`)
+    #[serde(skip_serializing_if = "Limits::is_empty", default)]
+    pub limits: Limits,
+    /// cgroup v2 limits
+    #[serde(skip_serializing_if = "CGroupLimits::is_empty", default)]
+    pub cgroup: CGroupLimits,
+    /// Services (names) that must be running before this one is started
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub depends_on: Vec<String>,
+    /// Service health check
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub healthcheck: Option<HealthCheck>,
+    /// Signal sent to stop the service
+    #[serde(
+        skip_serializing_if = "is_default_stop_signal",
+        default = "default_stop_signal"
+    )]
+    pub stop_signal: Signal,
+    /// Time to wait for the service to stop after `stop_signal`
+    #[serde(
+        with = "human::duration",
+        skip_serializing_if = "is_default_stop_timeout",
+        default = "default_stop_timeout"
+    )]
+    pub stop_timeout: Duration,
+    /// Signals sent when the service fails to stop within `stop_timeout`
+    #[serde(
+        skip_serializing_if = "is_default_stop_escalation",
+        default = "default_stop_escalation"
+    )]
+    pub stop_escalation: Vec<StopStep>,
+    /// Restart policy
+    #[serde(skip_serializing_if = "is_default_restart_policy", default)]
+    pub restart: RestartPolicy,
+    /// Maximum restarts within `restart_window`, the service fails once reached
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_restarts: Option<usize>,
+    /// Time window for `max_restarts`
+    #[serde(
+        with = "human::duration",
+        skip_serializing_if = "is_default_restart_window",
+        default = "default_restart_window"
+    )]
+    pub restart_window: Duration,
+    /// Memory usage above which the service is restarted
+    #[serde(
+        with = "human::size::option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub max_memory: Option<u64>,
+    /// Sustained CPU usage above which the service is restarted
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_cpu: Option<CpuThreshold>,
+    /// Exit codes considered as a successful termination, besides `0`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub success_exit_codes: Vec<c_int>,
+    /// Signals considered as a successful termination, besides `SIGTERM` and `stop_signal`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub success_signals: Vec<Signal>,
+    /// Signal sent to reload the service
+    #[serde(
+        skip_serializing_if = "is_default_reload_signal",
+        default = "default_reload_signal"
+    )]
+    pub reload_signal: Signal,
+    /// Command run to reload the service, used instead of `reload_signal`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub reload_command: Option<Hook>,
+    /// Command run before starting the service, the service crashes if it fails
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pre_start: Option<Hook>,
+    /// Command run once the service is started
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub post_start: Option<Hook>,
+    /// Command run once the service is terminated
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub post_stop: Option<Hook>,
     /// Running process informations
     #[serde(skip, default)]
     _info: Mutex<Arc<Info>>,
@@ -139,6 +332,12 @@ impl std::fmt::Debug for Service {
         if let Some(schedule) = &self.schedule {
             binding.field("schedule", schedule);
         }
+        if let Some(replica) = &self.replica {
+            binding.field("replica", replica);
+        }
+        if !self.depends_on.is_empty() {
+            binding.field("depends_on", &self.depends_on);
+        }
         binding.finish()
     }
 }
@@ -147,6 +346,14 @@ fn is_invalid_id(id: &ServiceId) -> bool {
     id == &SERVICE_ID_INVALID
 }
 
+fn default_autostart() -> bool {
+    true
+}
+
+fn is_autostart(autostart: &bool) -> bool {
+    *autostart
+}
+
 impl Service {
     pub fn new<T>(name: T, command: Command) -> Self
     where
@@ -158,7 +365,37 @@ impl Service {
             command,
             workdir: None,
             schedule: Default::default(),
+            concurrency_policy: Default::default(),
+            timeout: None,
+            catch_up: Default::default(),
+            max_lateness: None,
+            blackout: Vec::new(),
+            instances: default_instances(),
+            autostart: default_autostart(),
+            replica: None,
             watch: None,
+            user: None,
+            group: None,
+            supplementary_groups: Vec::new(),
+            limits: Limits::default(),
+            cgroup: CGroupLimits::default(),
+            depends_on: Vec::new(),
+            healthcheck: None,
+            stop_signal: default_stop_signal(),
+            stop_timeout: default_stop_timeout(),
+            stop_escalation: default_stop_escalation(),
+            restart: RestartPolicy::default(),
+            max_restarts: None,
+            restart_window: default_restart_window(),
+            max_memory: None,
+            max_cpu: None,
+            success_exit_codes: Vec::new(),
+            success_signals: Vec::new(),
+            reload_signal: default_reload_signal(),
+            reload_command: None,
+            pre_start: None,
+            post_start: None,
+            post_stop: None,
             _info: Default::default(),
             _stats: Default::default(),
         }
@@ -175,6 +412,29 @@ impl Service {
         } else if self.name.is_empty() {
             return Err(anyhow!("service `name` missing"));
         }
+        let on_change = match self.watch.as_ref().map(|watch| &watch.on_change) {
+            Some(OnChange::Run(hook)) => Some(hook),
+            _ => None,
+        };
+        for (name, hook) in [
+            ("reload_command", self.reload_command.as_ref()),
+            ("pre_start", self.pre_start.as_ref()),
+            ("post_start", self.post_start.as_ref()),
+            ("post_stop", self.post_stop.as_ref()),
+            ("on_change", on_change),
+        ] {
+            if hook.is_some_and(|hook| hook.command.path.is_empty()) {
+                return Err(anyhow!(
+                    "invalid `{name}` command, missing `path` for service `{}`",
+                    self.name
+                ));
+            }
+        }
+        self.credentials()
+            .map_err(|err| anyhow!("{err} for service `{}`", self.name))?;
+        if !self.autostart {
+            self.set_active(false);
+        }
         Ok(self)
     }
 
@@ -195,10 +455,10 @@ impl Service {
             self.stop();
         }
 
-        let launcher = match LAUNCHER_EXE.deref().as_ref() {
-            Some(launcher) => launcher,
-            None => {
-                tracing::error!("no launcher available");
+        let mut cmd = match self.make_command(&self.command) {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                tracing::error!(?err, "failed to prepare command");
                 return;
             }
         };
@@ -207,35 +467,157 @@ impl Service {
         // restarted but will prevent monitor from running waitpid
         // before we've set pid on this service
         let mut guard = self._info.lock().unwrap();
+        let logger = logger.into();
         let (out, err) = logger
-            .into()
-            .and_then(|l| l.make_pipe(self.id, &self.name).ok())
+            .and_then(|l| {
+                if self.schedule.is_some() {
+                    l.make_run_pipe(self.id, &self.name).ok()
+                } else {
+                    l.make_pipe(self.id, &self.name).ok()
+                }
+            })
             .map(|(out, err)| (out.into(), err.into()))
             .unwrap_or_else(|| (process::Stdio::inherit(), process::Stdio::inherit()));
 
-        let mut cmd = process::Command::new(launcher);
-        cmd.arg(self.command.path.as_str())
-            .args(&self.command.args)
-            .stdin(process::Stdio::null())
-            .stdout(out)
-            .stderr(err);
-        if let Some(workdir) = self.workdir.as_ref() {
-            cmd.current_dir(workdir);
-        }
-        if let Some(env) = self.command.env.as_ref() {
-            cmd.envs(env);
-        }
+        cmd.stdin(process::Stdio::null()).stdout(out).stderr(err);
 
         match cmd.spawn() {
             Ok(child) => {
                 let info = Arc::make_mut(&mut guard);
                 info.active = true;
                 info.set_running(child.id() as pid_t);
+                info.health = self.healthcheck.as_ref().map(|_| Health::Starting);
+                if let Some(run) = info.history.back_mut() {
+                    run.log_file = logger.and_then(|l| l.run_file(self.id));
+                }
             }
             Err(err) => tracing::error!(?err, "failed to spawn process"),
         }
     }
 
+    /// Build a [process::Command] running `command` through the launcher
+    ///
+    /// Uses the service environment (workdir, env files, interpolated
+    /// variables), `command` own environment is loaded on top of it.
+    /// Fails if the launcher can't be found.
+    pub(crate) fn make_command(&self, command: &Command) -> Result<process::Command> {
+        let launcher = LAUNCHER_EXE
+            .deref()
+            .as_ref()
+            .ok_or_else(|| anyhow!("no launcher available"))?;
+        let mut env = Environment::default();
+        env.insert(ENV_INSTANCE_ID, self.instance_id());
+        self.command.load_env(&mut env)?;
+        if !std::ptr::eq(command, &self.command) {
+            command.load_env(&mut env)?;
+        }
+
+        let mut cmd = process::Command::new(launcher);
+        cmd.arg(command.interpolate(&env, &command.path)?);
+        for arg in command.args.iter() {
+            cmd.arg(command.interpolate(&env, arg)?);
+        }
+        if let Some(workdir) = self.workdir.as_ref() {
+            cmd.current_dir(self.command.interpolate(&env, workdir)?);
+        }
+        cmd.envs(env.iter());
+        if let Some(user) = self.user.as_ref() {
+            cmd.env(LAUNCHER_ENV_USER, user);
+        }
+        if let Some(group) = self.group.as_ref() {
+            cmd.env(LAUNCHER_ENV_GROUP, group);
+        }
+        if !self.supplementary_groups.is_empty() {
+            cmd.env(LAUNCHER_ENV_GROUPS, self.supplementary_groups.join(","));
+        }
+        if !self.limits.is_empty() {
+            match serde_json::to_string(&self.limits) {
+                Ok(limits) => {
+                    cmd.env(LAUNCHER_ENV_LIMITS, limits);
+                }
+                Err(err) => tracing::error!(?err, "failed to serialize limits"),
+            }
+        }
+        if let Some(cgroup) = self.info().cgroup.as_ref() {
+            cmd.env(LAUNCHER_ENV_CGROUP, cgroup);
+        }
+        Ok(cmd)
+    }
+
+    /// Set the cgroup the service is spawned in
+    ///
+    /// - `oom_kills` is the cgroup current OOM kill counter
+    pub fn set_cgroup(&self, cgroup: Option<PathBuf>, oom_kills: u64) {
+        let mut guard = self._info.lock().unwrap();
+        let info = Arc::make_mut(&mut guard);
+        info.cgroup = cgroup;
+        info.cgroup_oom_kills = oom_kills;
+    }
+
+    /// Record the cgroup OOM kill counter once the service got killed
+    ///
+    /// Returns `true` if the service was killed by the OOM killer.
+    #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self), ret(level = "TRACE"))]
+    pub fn set_oom_kills(&self, oom_kills: u64) -> bool {
+        let mut guard = self._info.lock().unwrap();
+        Arc::make_mut(&mut guard).set_oom_kills(oom_kills)
+    }
+
+    /// Instance index, `0` for the parent service
+    pub fn instance_id(&self) -> usize {
+        self.replica.as_ref().map_or(0, |replica| replica.index)
+    }
+
+    /// Parent service name, own name for the parent service
+    pub fn parent_name(&self) -> &str {
+        self.replica
+            .as_ref()
+            .map_or(self.name.as_str(), |replica| replica.parent.as_str())
+    }
+
+    /// Configured number of instances
+    pub fn instances(&self) -> usize {
+        self.instances.load(Ordering::Relaxed)
+    }
+
+    /// Create the `index` replica of this service
+    ///
+    /// The replica shares the service configuration, it is named after its
+    /// parent and index (ex: `worker.1`) and gets a new id.
+    pub fn replica(&self, index: usize) -> Result<Service> {
+        let mut ret: Service = serde_json::from_value(serde_json::to_value(self)?)?;
+        let replica = Replica {
+            parent: self.parent_name().to_string(),
+            index,
+        };
+        ret.id = SERVICE_ID_INVALID;
+        ret.name = replica.name();
+        ret.instances = default_instances();
+        ret.replica = Some(replica);
+        ret.validate()
+    }
+
+    /// Expand a service into its instances, the parent service first
+    pub fn expand(self) -> Result<Vec<Service>> {
+        let instances = self.instances();
+        if instances == 0 {
+            return Err(anyhow!("invalid `instances` for service `{}`", self.name));
+        }
+        let replicas = (1..instances)
+            .map(|index| self.replica(index))
+            .collect::<Result<Vec<Service>>>()?;
+        Ok([self].into_iter().chain(replicas).collect())
+    }
+
+    /// Resolve service credentials
+    pub fn credentials(&self) -> Result<Credentials> {
+        Credentials::resolve(
+            self.user.as_deref(),
+            self.group.as_deref(),
+            &self.supplementary_groups,
+        )
+    }
+
     #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self), ret(level = "TRACE"))]
     pub fn set_active(&self, value: bool) {
         let mut guard = self._info.lock().unwrap();
@@ -250,15 +632,19 @@ impl Service {
     pub fn stop(&self) {
         {
             let mut guard = self._info.lock().unwrap();
-            Arc::make_mut(&mut guard).active = false;
+            let info = Arc::make_mut(&mut guard);
+            info.active = false;
+            info.stopping = info.pid.is_some();
         }
 
         if let Some(pid) = self.info().pid {
             tracing::debug!(pid, "trying to stop");
-            if self.terminate(pid, SIGTERM, &Duration::from_secs(5)) {
-                tracing::trace!(pid, "process terminated");
-            } else if self.terminate(pid, signal::SIGKILL, &Duration::from_secs(10)) {
-                tracing::trace!(pid, "process killed");
+            if let Some(step) = [StopStep::new(self.stop_signal, self.stop_timeout)]
+                .iter()
+                .chain(self.stop_escalation.iter())
+                .find(|step| self.terminate(pid, step.signal, &step.timeout))
+            {
+                tracing::trace!(pid, signal = ?step.signal, "process terminated");
             } else {
                 tracing::error!("failed to kill process");
             }
@@ -267,21 +653,47 @@ impl Service {
         }
     }
 
+    /// Send a signal to the running process group
+    #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self), err)]
+    pub fn signal(&self, signal: Signal) -> Result<()> {
+        let pid = self
+            .info()
+            .pid
+            .ok_or_else(|| anyhow!("service `{}` is not running", self.name))?;
+        Signal::kill(Self::kill_target(pid), signal)
+    }
+
+    /// Signal target for a process, its group if it leads one
+    pub(crate) fn kill_target(pid: pid_t) -> pid_t {
+        /* negative pid targets the process group */
+        if getpgid(pid) == pid { -pid } else { pid }
+    }
+
     /// send a termination signal, wait for process end
     ///
+    /// The signal is sent to the whole process group, this waits for all
+    /// its processes to terminate.
+    ///
     /// This will not update the service `info`, the `Monitor` thread should
     /// do using `waitpid`
     #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self), ret)]
     fn terminate(&self, pid: pid_t, signal: Signal, timeout: &Duration) -> bool {
-        if Signal::kill(pid, signal).is_err() {
+        let target = Self::kill_target(pid);
+        if Signal::kill(target, signal).is_err() {
             // already dead
             return true;
         }
 
         let start = std::time::Instant::now();
-        while self.info().pid.is_some_and(|p| pid == p) {
-            if let Some((pid, status)) = waitpid(pid, false) {
-                self.set_terminated(pid, status);
+        loop {
+            while let Some((child, status)) = waitpid(target, false) {
+                if child == pid {
+                    self.set_terminated(pid, status);
+                } else {
+                    tracing::debug!(pid = child, "orphan process terminated");
+                }
+            }
+            if self.info().pid.is_none_or(|p| pid != p) && !Signal::exists(target) {
                 return true;
             } else if &start.elapsed() < timeout {
                 std::thread::sleep(std::time::Duration::from_millis(10));
@@ -289,7 +701,6 @@ impl Service {
                 return false;
             }
         }
-        true
     }
 
     #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self), ret(level = "TRACE"))]
@@ -305,15 +716,20 @@ impl Service {
             );
         } else if WIFSIGNALED(status) {
             let signal = Signal(WTERMSIG(status));
+            Arc::make_mut(&mut guard).set_exit(status);
             tracing::info!(
                 id = self.id,
                 name = self.name,
                 pid,
                 ?signal,
+                core_dumped = WCOREDUMP(status),
                 "service terminated by signal"
             );
 
-            if signal == SIGTERM {
+            if signal == SIGTERM
+                || signal == self.stop_signal
+                || self.success_signals.contains(&signal)
+            {
                 Arc::make_mut(&mut guard).set_finished();
             } else {
                 Arc::make_mut(&mut guard).set_crashed();
@@ -321,8 +737,9 @@ impl Service {
         } else if WIFEXITED(status) {
             let code = WEXITSTATUS(status);
             tracing::info!(id = self.id, name = self.name, pid, code, "service exited");
+            Arc::make_mut(&mut guard).set_exit(status);
 
-            if code == 0 {
+            if code == 0 || self.success_exit_codes.contains(&code) {
                 Arc::make_mut(&mut guard).set_finished();
             } else {
                 Arc::make_mut(&mut guard).set_crashed();
@@ -363,6 +780,26 @@ impl Service {
         Arc::make_mut(&mut guard).set_stopped();
     }
 
+    /// Set service as [Status::Crashed] when it failed to start
+    ///
+    /// Must be called from [crate::monitor::Monitor]
+    #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self))]
+    pub fn set_start_failed(&self) {
+        let mut guard = self._info.lock().unwrap();
+        Arc::make_mut(&mut guard).set_start_failed();
+    }
+
+    /// End of the `blackout` window `at` falls in, if any
+    pub fn blackout_end(&self, at: DateTime<Local>) -> Option<DateTime<Local>> {
+        blackout_end(&self.blackout, at)
+    }
+
+    /// Set what triggers the next run, recorded in the run history
+    pub fn set_trigger(&self, trigger: RunTrigger) {
+        let mut guard = self._info.lock().unwrap();
+        Arc::make_mut(&mut guard).trigger = Some(trigger);
+    }
+
     /// Set service as [Status::Running]
     ///
     /// Must be called from [crate::monitor::Monitor]
@@ -372,6 +809,25 @@ impl Service {
         Arc::make_mut(&mut guard).set_running(pid);
     }
 
+    /// Record a health check result
+    ///
+    /// Must be called from [crate::monitor::Monitor]
+    #[tracing::instrument(level = "DEBUG", fields(name=self.name, id=self.id), skip(self), ret)]
+    pub fn set_health(&self, healthy: bool) -> Option<Health> {
+        let healthcheck = self.healthcheck.as_ref()?;
+        let mut guard = self._info.lock().unwrap();
+        let in_start_period = guard
+            .uptime()
+            .is_some_and(|uptime| uptime < healthcheck.start_period);
+        let info = Arc::make_mut(&mut guard);
+        let previous = info.health;
+        let health = info.set_health(healthy, healthcheck.retries, in_start_period);
+        if previous != Some(health) {
+            tracing::info!("health {previous:?} -> {health:?}");
+        }
+        Some(health)
+    }
+
     pub fn info(&self) -> Arc<Info> {
         Arc::clone(&self._info.lock().unwrap())
     }
@@ -384,6 +840,47 @@ impl Service {
         *self._stats.lock().unwrap() = Arc::new(stats);
     }
 
+    /// Check the latest stats against `max_memory` and `max_cpu`
+    ///
+    /// Returns the exceeded threshold, if any.
+    pub fn check_thresholds(&self) -> Option<RestartReason> {
+        let stats = self.stats();
+        if stats.uptime.is_none() {
+            None
+        } else if self.max_memory.is_some_and(|max| stats.mem_rss > max) {
+            Some(RestartReason::MaxMemory)
+        } else if let Some(max_cpu) = self.max_cpu.as_ref() {
+            let mut guard = self._info.lock().unwrap();
+            Arc::make_mut(&mut guard)
+                .set_cpu_exceeded(stats.cpu_usage > max_cpu.usage, max_cpu.duration)
+                .then_some(RestartReason::MaxCpu)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_restart_reason(&self, reason: RestartReason) {
+        let mut guard = self._info.lock().unwrap();
+        let info = Arc::make_mut(&mut guard);
+        info.restart_reason = Some(reason);
+        info.cpu_exceeded_since = None;
+    }
+
+    /// Account for an automatic restart
+    ///
+    /// Returns `false` once `max_restarts` is reached within `restart_window`,
+    /// the service is then marked as [Status::Failed].
+    #[tracing::instrument(level = "INFO", fields(name=self.name, id=self.id), skip(self), ret(level = "TRACE"))]
+    pub fn register_restart(&self) -> bool {
+        match self.max_restarts {
+            Some(max_restarts) => {
+                let mut guard = self._info.lock().unwrap();
+                Arc::make_mut(&mut guard).register_restart(max_restarts, self.restart_window)
+            }
+            None => true,
+        }
+    }
+
     /// Update the restart throttle
     ///
     /// - `last_uptime` is in [crate::monitor::Monitor::restart_interval] slices
@@ -411,7 +908,37 @@ impl Default for Service {
             command: Default::default(),
             workdir: None,
             schedule: Default::default(),
+            concurrency_policy: Default::default(),
+            timeout: None,
+            catch_up: Default::default(),
+            max_lateness: None,
+            blackout: Vec::new(),
+            instances: default_instances(),
+            autostart: default_autostart(),
+            replica: None,
             watch: None,
+            user: None,
+            group: None,
+            supplementary_groups: Vec::new(),
+            limits: Limits::default(),
+            cgroup: CGroupLimits::default(),
+            depends_on: Vec::new(),
+            healthcheck: None,
+            stop_signal: default_stop_signal(),
+            stop_timeout: default_stop_timeout(),
+            stop_escalation: default_stop_escalation(),
+            restart: RestartPolicy::default(),
+            max_restarts: None,
+            restart_window: default_restart_window(),
+            max_memory: None,
+            max_cpu: None,
+            success_exit_codes: Vec::new(),
+            success_signals: Vec::new(),
+            reload_signal: default_reload_signal(),
+            reload_command: None,
+            pre_start: None,
+            post_start: None,
+            post_stop: None,
             _info: Default::default(),
             _stats: Default::default(),
         }
@@ -427,7 +954,7 @@ impl Drop for Service {
 #[cfg(test)]
 mod tests {
     #[cfg(target_os = "linux")]
-    use crate::utils::libc::waitpid;
+    use crate::utils::libc::{getpgid, waitpid};
     use crate::utils::signal::SignalSet;
     #[cfg(target_os = "linux")]
     use std::{
@@ -438,8 +965,8 @@ mod tests {
     use crate::{
         monitor::Monitor,
         utils::{
-            kill_on_drop,
-            signal::{SIGALRM, SIGCHLD, SIGTERM},
+            MkTemp, kill_on_drop,
+            signal::{self, SIGALRM, SIGCHLD, SIGTERM},
             wait_for,
         },
     };
@@ -507,6 +1034,31 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn success_exit_codes() -> Result<()> {
+        (SignalSet::empty() + SIGCHLD).block()?;
+        let mon = Arc::new(Monitor::default());
+        let mut srv = Service::new("exit", Command::new("sh", ["-c", "exit 3"]));
+        srv.success_exit_codes = vec![3];
+        srv.start(None);
+        let exit = mon.insert(srv);
+        let mut srv = Service::new("signal", Command::new("sleep", ["300"]));
+        srv.success_signals = vec![signal::SIGUSR1];
+        srv.start(None);
+        let signal = mon.insert(srv);
+        Signal::kill(signal.info().pid.unwrap(), signal::SIGUSR1)?;
+
+        wait_for!(
+            mon.on_sigchld() != 0 && exit.info().pid.is_none() && signal.info().pid.is_none()
+        )
+        .expect("services should terminate");
+        assert_eq!(exit.info().status, Status::Finished);
+        assert_eq!(signal.info().status, Status::Finished);
+        assert_eq!(exit.info().crashed + signal.info().crashed, 0);
+        Ok(())
+    }
+
     #[test]
     #[serial(waitpid)]
     fn stop() -> Result<()> {
@@ -535,6 +1087,171 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn stop_sequence() -> Result<()> {
+        (SignalSet::empty() + SIGCHLD).block()?;
+        let mon = Arc::new(Monitor::default());
+        let join_handle = {
+            /* Monitor is handling dead processes */
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        let mut srv = Service::new("sigint", Command::new("sh", ["-c", "exec sleep 300"]));
+        srv.stop_signal = signal::SIGINT;
+        srv.start(None);
+        let service = mon.insert(srv);
+        assert!(service.info().pid.is_some_and(|pid| pid > 0));
+        service.stop();
+        assert_eq!(service.info().pid, None);
+        assert_eq!(service.info().status, Status::Finished);
+        assert_eq!(
+            service.info().last_exit.as_ref().and_then(|e| e.signal),
+            Some(signal::SIGINT)
+        );
+
+        let mut srv = Service::new(
+            "escalation",
+            Command::new("sh", ["-c", "trap '' TERM; exec sleep 300"]),
+        );
+        srv.stop_timeout = Duration::from_millis(200);
+        srv.start(None);
+        let service = mon.insert(srv);
+        /* let the shell install its trap */
+        std::thread::sleep(Duration::from_millis(100));
+        service.stop();
+        assert_eq!(service.info().pid, None);
+        assert_eq!(service.info().status, Status::Crashed);
+        assert_eq!(
+            service.info().last_exit.as_ref().and_then(|e| e.signal),
+            Some(signal::SIGKILL)
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    #[cfg(target_os = "linux")]
+    fn stop_group() -> Result<()> {
+        Monitor::init()?;
+        let service = Service::new("test", Command::new("sh", ["-c", "sleep 300 | sleep 300"]));
+        service.start(None);
+        let mon = Arc::new(Monitor::default());
+        let service = mon.insert(service);
+
+        let join_handle = {
+            /* Monitor is handling dead processes */
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        let pid = service.info().pid.expect("should have spawned");
+        wait_for!(getpgid(pid) == pid).expect("not in its own process group");
+
+        service.stop();
+        assert_eq!(service.info().pid, None);
+        assert!(!Signal::exists(-pid), "process group should be terminated");
+        assert!(
+            mon.find_by_pgid(pid)
+                .is_some_and(|srv| srv.id == service.id)
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    #[cfg(target_os = "linux")]
+    fn credentials() -> Result<()> {
+        if unsafe { libc::getuid() } != 0 {
+            tracing::warn!("not root, skipping test");
+            return Ok(());
+        }
+        (SignalSet::empty() + SIGCHLD).block()?;
+        let mut srv = Service::new("test", Command::new("sh", ["-c", "exec sleep 300"]));
+        srv.user = Some("65534".into());
+        srv.group = Some("65534".into());
+        srv.supplementary_groups = vec!["0".into()];
+        srv.start(None);
+        let mon = Arc::new(Monitor::default());
+        let service = mon.insert(srv);
+
+        let join_handle = {
+            /* Monitor is handling dead processes */
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        let pid = service.info().pid.expect("should have spawned");
+        let status =
+            || -> Result<String> { Ok(std::fs::read_to_string(format!("/proc/{pid}/status"))?) };
+        wait_for!(status()?.contains("Uid:\t65534\t")).expect("user not applied");
+        let status = status()?;
+        assert!(status.contains("Gid:\t65534\t"), "{status}");
+        assert!(status.contains("Groups:\t0 \n"), "{status}");
+        service.stop();
+
+        /* supplementary groups default to the user memberships */
+        let mut srv = Service::new("test_groups", Command::new("sh", ["-c", "exec sleep 300"]));
+        srv.user = Some("nobody".into());
+        srv.start(None);
+        let service = mon.insert(srv);
+        let pid = service.info().pid.expect("should have spawned");
+        let status =
+            || -> Result<String> { Ok(std::fs::read_to_string(format!("/proc/{pid}/status"))?) };
+        wait_for!(status()?.contains("Uid:\t65534\t")).expect("user not applied");
+        let status = status()?;
+        assert!(status.contains("Groups:\t65534 \n"), "{status}");
+
+        service.stop();
+        Ok(())
+    }
+
+    #[test]
+    #[serial(waitpid)]
+    #[cfg(target_os = "linux")]
+    fn limits() -> Result<()> {
+        (SignalSet::empty() + SIGCHLD).block()?;
+        let mut srv = Service::new("test", Command::new("sh", ["-c", "exec sleep 300"]));
+        srv.limits = serde_yaml_ng::from_str(
+            "{ nofile: { soft: 100, hard: 200 }, core: 0, stack: { soft: 1MiB } }",
+        )?;
+        srv.start(None);
+        let mon = Arc::new(Monitor::default());
+        let service = mon.insert(srv);
+
+        let join_handle = {
+            /* Monitor is handling dead processes */
+            let mon = Arc::clone(&mon);
+            std::thread::spawn(move || mon.run())
+        };
+        let _drop_guard = kill_on_drop(join_handle);
+
+        let pid = service.info().pid.expect("should have spawned");
+        let limits =
+            || -> Result<String> { Ok(std::fs::read_to_string(format!("/proc/{pid}/limits"))?) };
+        wait_for!(limits()?.contains("Max open files            100                  200"))
+            .expect("limits not applied");
+        let limits = limits()?;
+        assert!(
+            limits.contains("Max core file size        0                    0"),
+            "{limits}"
+        );
+        /* unset hard limit is kept from the launching process */
+        let hard = match Resource::Stack.get()?.hard {
+            Some(LIMIT_UNLIMITED) | None => "unlimited".to_string(),
+            Some(hard) => hard.to_string(),
+        };
+        let stack = format!("{:<25} {:<20} {:<20}", "Max stack size", 1 << 20, hard);
+        assert!(limits.contains(&stack), "{limits}");
+
+        service.stop();
+        Ok(())
+    }
+
     #[test]
     fn serde() {
         let srv = Service::new("test", Command::new("sh", ["-c", "sleep 300"]));
@@ -574,6 +1291,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial(waitpid)]
+    fn environment() -> Result<()> {
+        let dir = MkTemp::dir("ppm-env")?;
+        let env_file = dir.join("test.env");
+        std::fs::write(&env_file, "FROM_FILE=file\n")?;
+        let mut srv = Service::new(
+            "test",
+            Command::new(
+                "sh",
+                [
+                    "-c",
+                    "echo \"$FROM_ENV ${FROM_FILE} $PPM_INSTANCE_ID\" > ${OUT}; exec sleep 300",
+                ],
+            ),
+        );
+        srv.workdir = Some(String::from("${PPM_NO_SUCH_VAR:-/}"));
+        srv.command.env_file = vec![env_file.clone()];
+        srv.command.interpolate = true;
+        srv.command.env = Some(
+            [
+                ("FROM_ENV", "${FROM_FILE}-env"),
+                ("OUT", &format!("{}/out", dir.to_string_lossy())),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        );
+
+        let out = dir.join("out");
+        srv.start(None);
+        wait_for!(std::fs::read_to_string(&out).is_ok_and(|out| out == "file-env file 0\n"))
+            .expect("invalid environment");
+
+        std::fs::write(&env_file, "FROM_FILE=reloaded\n")?;
+        srv.restart(None);
+        wait_for!(
+            std::fs::read_to_string(&out).is_ok_and(|out| out == "reloaded-env reloaded 0\n")
+        )
+        .expect("env file not reloaded");
+        srv.stop();
+
+        std::fs::remove_file(&env_file)?;
+        srv.make_command(&srv.command)
+            .expect_err("env file is missing");
+
+        /* values are used as-is unless interpolation is enabled */
+        let script = "x=1; echo ${x} $$ ${";
+        let srv = Service::new("test", Command::new("sh", ["-c", script]));
+        let cmd = srv.make_command(&srv.command)?;
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["sh", "-c", script]);
+        Ok(())
+    }
+
     #[test]
     #[serial(waitpid)]
     #[cfg(target_os = "linux")]
@@ -0,0 +1,321 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T09:41:26
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, Local, NaiveTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+
+use crate::utils::serializers::human;
+
+/// Maximum number of adjacent windows merged when looking for a window end
+const MAX_CHAINED_WINDOWS: usize = 16;
+
+/// Time window during which automatic restarts and scheduled runs are deferred
+///
+/// Can be written as a plain `HH:MM-HH:MM` time-of-day range, or as a map with
+/// either `from` and `to`, or `cron` and `duration`, and an optional `timezone`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "BlackoutDef", into = "BlackoutDef")]
+pub struct Blackout {
+    pub window: Window,
+    /// Time zone the window is evaluated in, defaults to local time
+    pub timezone: Option<Tz>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)] /* a few per service */
+pub enum Window {
+    /// Daily time-of-day range, spans midnight when `to` is before `from`
+    Daily { from: NaiveTime, to: NaiveTime },
+    /// Starts on each `cron` occurrence, lasts `duration`
+    Cron { cron: Cron, duration: Duration },
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BlackoutDef {
+    Range(String),
+    Full {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        to: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cron: Option<String>,
+        #[serde(
+            with = "human::duration",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        duration: Option<Duration>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+}
+
+impl Blackout {
+    pub fn new(window: Window) -> Self {
+        Self {
+            window,
+            timezone: None,
+        }
+    }
+
+    /// End of the window `at` falls in, `None` when outside of the window
+    pub fn end(&self, at: &DateTime<Local>) -> Option<DateTime<Local>> {
+        match self.timezone {
+            Some(tz) => self
+                .end_in(&at.with_timezone(&tz))
+                .map(|end| end.with_timezone(&Local)),
+            None => self.end_in(at),
+        }
+    }
+
+    fn end_in<T: TimeZone>(&self, at: &DateTime<T>) -> Option<DateTime<T>> {
+        match &self.window {
+            Window::Daily { from, to } => {
+                let time = at.time();
+                let date = at.date_naive();
+                let end_date = if from <= to {
+                    (*from <= time && time < *to).then_some(date)
+                } else if time >= *from {
+                    date.succ_opt()
+                } else {
+                    (time < *to).then_some(date)
+                };
+                let end = end_date?.and_time(*to);
+                let tz = at.timezone();
+                /* `to` may not exist on DST transitions */
+                tz.from_local_datetime(&end).earliest().or_else(|| {
+                    tz.from_local_datetime(&(end + TimeDelta::hours(1)))
+                        .earliest()
+                })
+            }
+            Window::Cron { cron, duration } => {
+                let start = cron
+                    .find_next_occurrence(&(at.clone() - *duration), false)
+                    .ok()?;
+                (start <= *at).then(|| start + *duration)
+            }
+        }
+    }
+}
+
+/// End of the blackout `windows` `at` falls in, adjacent windows are merged
+pub fn blackout_end(windows: &[Blackout], at: DateTime<Local>) -> Option<DateTime<Local>> {
+    let mut end = None;
+    for _ in 0..MAX_CHAINED_WINDOWS {
+        match windows
+            .iter()
+            .filter_map(|w| w.end(&end.unwrap_or(at)))
+            .max()
+        {
+            Some(next) => end = Some(next),
+            None => break,
+        }
+    }
+    end
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    ["%H:%M:%S", "%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveTime::parse_from_str(time.trim(), fmt).ok())
+        .ok_or_else(|| anyhow!("invalid time `{time}`"))
+}
+
+fn daily(from: &str, to: &str) -> Result<Window> {
+    let (from, to) = (parse_time(from)?, parse_time(to)?);
+    if from == to {
+        bail!("blackout window `from` and `to` must differ");
+    }
+    Ok(Window::Daily { from, to })
+}
+
+impl FromStr for Blackout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (from, to) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("invalid blackout window `{s}`, expecting `HH:MM-HH:MM`"))?;
+        Ok(Self::new(daily(from, to)?))
+    }
+}
+
+impl TryFrom<BlackoutDef> for Blackout {
+    type Error = anyhow::Error;
+
+    fn try_from(value: BlackoutDef) -> Result<Self> {
+        match value {
+            BlackoutDef::Range(range) => range.parse(),
+            BlackoutDef::Full {
+                from,
+                to,
+                cron,
+                duration,
+                timezone,
+            } => {
+                let timezone = timezone
+                    .map(|tz| Tz::from_str(&tz).map_err(|_| anyhow!("unknown timezone `{tz}`")))
+                    .transpose()?;
+                let window = match (from, to, cron, duration) {
+                    (Some(from), Some(to), None, None) => daily(&from, &to)?,
+                    (None, None, Some(cron), Some(duration)) => {
+                        if duration.is_zero() {
+                            bail!("blackout `duration` must not be zero");
+                        }
+                        Window::Cron {
+                            cron: Cron::from_str(&cron)
+                                .map_err(|err| anyhow!("invalid cron `{cron}`: {err}"))?,
+                            duration,
+                        }
+                    }
+                    _ => {
+                        bail!("blackout requires either `from` and `to`, or `cron` and `duration`")
+                    }
+                };
+                Ok(Self { window, timezone })
+            }
+        }
+    }
+}
+
+impl From<Blackout> for BlackoutDef {
+    fn from(value: Blackout) -> Self {
+        let timezone = value.timezone.map(|tz| tz.name().to_string());
+        let time = |time: NaiveTime| time.format("%H:%M:%S").to_string();
+        match value.window {
+            Window::Daily { from, to } if timezone.is_none() => {
+                BlackoutDef::Range(format!("{}-{}", time(from), time(to)))
+            }
+            Window::Daily { from, to } => BlackoutDef::Full {
+                from: Some(time(from)),
+                to: Some(time(to)),
+                cron: None,
+                duration: None,
+                timezone,
+            },
+            Window::Cron { cron, duration } => BlackoutDef::Full {
+                from: None,
+                to: None,
+                cron: Some(cron.to_string()),
+                duration: Some(duration),
+                timezone,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+    use serde_yaml_ng as yaml;
+
+    fn utc(h: u32, m: u32) -> DateTime<Local> {
+        Utc.from_utc_datetime(
+            &NaiveDate::from_ymd_opt(2026, 10, 17)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn serde() -> Result<()> {
+        let blackout: Blackout = yaml::from_str("'23:55-00:10'")?;
+        assert_eq!(
+            blackout.window,
+            Window::Daily {
+                from: NaiveTime::from_hms_opt(23, 55, 0).unwrap(),
+                to: NaiveTime::from_hms_opt(0, 10, 0).unwrap(),
+            }
+        );
+        assert_eq!(blackout, yaml::from_str(&yaml::to_string(&blackout)?)?);
+
+        let blackout: Blackout =
+            yaml::from_str("{ from: '23:55', to: '00:10', timezone: Europe/Paris }")?;
+        assert_eq!(blackout.timezone, Some(chrono_tz::Europe::Paris));
+        assert_eq!(blackout, yaml::from_str(&yaml::to_string(&blackout)?)?);
+
+        let blackout: Blackout = yaml::from_str("{ cron: '0 55 23 * * *', duration: 15m }")?;
+        assert!(matches!(blackout.window, Window::Cron { duration, .. }
+            if duration == Duration::from_mins(15)));
+        assert_eq!(blackout, yaml::from_str(&yaml::to_string(&blackout)?)?);
+
+        for (def, err) in [
+            ("'23:55'", "HH:MM-HH:MM"),
+            ("'10:00-10:00'", "must differ"),
+            ("'25:00-10:00'", "25:00"),
+            ("{ from: '23:55' }", "either"),
+            ("{ cron: '0 55 23 * * *' }", "either"),
+            ("{ cron: '0 55 23 * * *', duration: 0s }", "zero"),
+            (
+                "{ from: '23:55', to: '00:10', timezone: Mars/Olympus }",
+                "Mars",
+            ),
+        ] {
+            let ret = yaml::from_str::<Blackout>(def).expect_err(def);
+            assert!(ret.to_string().contains(err), "{def}: {ret}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn windows() -> Result<()> {
+        let mut blackout: Blackout = yaml::from_str("'23:55-00:10'")?;
+        blackout.timezone = Some(chrono_tz::UTC);
+        assert_eq!(blackout.end(&utc(23, 54)), None);
+        assert_eq!(
+            blackout.end(&utc(23, 55)),
+            Some(utc(0, 10) + TimeDelta::days(1))
+        );
+        assert_eq!(blackout.end(&utc(0, 5)), Some(utc(0, 10)));
+        assert_eq!(blackout.end(&utc(0, 10)), None);
+
+        let mut day: Blackout = yaml::from_str("'09:00-10:00'")?;
+        day.timezone = Some(chrono_tz::UTC);
+        assert_eq!(day.end(&utc(9, 30)), Some(utc(10, 0)));
+        assert_eq!(day.end(&utc(10, 30)), None);
+
+        let mut cron: Blackout = yaml::from_str("{ cron: '0 0 10 * * *', duration: 1h }")?;
+        cron.timezone = Some(chrono_tz::UTC);
+        assert_eq!(cron.end(&utc(9, 59)), None);
+        assert_eq!(cron.end(&utc(10, 0)), Some(utc(11, 0)));
+        assert_eq!(cron.end(&utc(10, 59)), Some(utc(11, 0)));
+        assert_eq!(cron.end(&utc(11, 0)), None);
+
+        /* adjacent windows are merged */
+        assert_eq!(
+            blackout_end(&[cron.clone(), day], utc(9, 30)),
+            Some(utc(11, 0))
+        );
+        assert_eq!(blackout_end(&[cron], utc(12, 0)), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T09:40:02
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::serializers::human;
+
+/// cgroup v2 limits, requires the daemon `cgroup` to be configured
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CGroupLimits {
+    /// Memory limit (`memory.max`)
+    #[serde(
+        with = "human::size::option",
+        skip_serializing_if = "Option::is_none",
+        default
+    )]
+    pub memory_max: Option<u64>,
+    /// CPU limit in number of CPUs (`cpu.max`)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub cpu_max: Option<f64>,
+    /// Maximum number of processes (`pids.max`)
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pids_max: Option<u64>,
+}
+
+impl CGroupLimits {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let limits: CGroupLimits = yaml::from_str("{ memory_max: 512MiB, cpu_max: 1.5 }")?;
+        assert_eq!(
+            limits,
+            CGroupLimits {
+                memory_max: Some(512 << 20),
+                cpu_max: Some(1.5),
+                pids_max: None
+            }
+        );
+        assert_eq!(
+            yaml::to_string(&limits)?,
+            "memory_max: 512MiB\ncpu_max: 1.5\n"
+        );
+        assert!(yaml::from_str::<CGroupLimits>("{}")?.is_empty());
+        Ok(())
+    }
+}
@@ -21,18 +21,30 @@
 ** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
 */
 
-use std::collections::HashMap;
-
+use anyhow::Result;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use std::{ops::Not, path::PathBuf};
+
+use super::Environment;
 
-#[derive(Debug, PartialEq, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Command {
     pub path: String,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub args: Vec<String>,
+    /// Environment variables, values may reference earlier keys (`${VAR}`)
+    /// when `interpolate` is set
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub env: Option<HashMap<String, String>>,
+    pub env: Option<IndexMap<String, String>>,
+    /// dotenv files, loaded before `env` on each start
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub env_file: Vec<PathBuf>,
+    /// Interpolate `${VAR}` references in `path`, `args`, `env` values (and
+    /// the service `workdir`), values are used as-is otherwise
+    #[serde(skip_serializing_if = "Not::not")]
+    pub interpolate: bool,
 }
 
 impl Command {
@@ -46,6 +58,32 @@ impl Command {
             path: path.to_string(),
             args: args.into_iter().map(|x| x.to_string()).collect(),
             env: None,
+            env_file: Vec::new(),
+            interpolate: false,
+        }
+    }
+
+    /// Load `env_file` and `env` variables in `env`
+    pub fn load_env(&self, env: &mut Environment) -> Result<()> {
+        for path in self.env_file.iter() {
+            env.load_file(path)?;
+        }
+        for (key, value) in self.env.iter().flatten() {
+            if self.interpolate {
+                env.set(key, value)?;
+            } else {
+                env.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Interpolate `value` if enabled
+    pub fn interpolate(&self, env: &Environment, value: &str) -> Result<String> {
+        if self.interpolate {
+            env.interpolate(value)
+        } else {
+            Ok(value.to_string())
         }
     }
 }
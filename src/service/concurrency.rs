@@ -0,0 +1,59 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T10:58:03
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use serde::{Deserialize, Serialize};
+
+/// What to do when a scheduled run is due while the previous one is still running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConcurrencyPolicy {
+    /// stop the running process and start a new one
+    #[default]
+    Replace,
+    /// skip the new run
+    Skip,
+    /// start the new run once the running one terminated, at most one run is queued
+    Queue,
+}
+
+pub(super) fn is_default_concurrency_policy(policy: &ConcurrencyPolicy) -> bool {
+    policy == &ConcurrencyPolicy::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        assert_eq!(
+            yaml::from_str::<ConcurrencyPolicy>("skip")?,
+            ConcurrencyPolicy::Skip
+        );
+        assert_eq!(yaml::to_string(&ConcurrencyPolicy::Queue)?, "queue\n");
+        yaml::from_str::<ConcurrencyPolicy>("forbid").expect_err("unknown policy");
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T14:08:32
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Context, Result, anyhow};
+use indexmap::IndexMap;
+use std::path::Path;
+
+/// Service environment
+///
+/// Values are interpolated (`${VAR}`, `${VAR:-default}`) using earlier keys
+/// and the daemon's environment, `$$` is an escaped `$`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Environment(IndexMap<String, String>);
+
+impl Environment {
+    /// Get a variable, from this environment or the daemon's one
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned().or_else(|| std::env::var(key).ok())
+    }
+
+    /// Set a variable, without interpolation
+    pub fn insert<K: ToString, V: ToString>(&mut self, key: K, value: V) {
+        self.0.insert(key.to_string(), value.to_string());
+    }
+
+    /// Set a variable, interpolating its value
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = self
+            .interpolate(value)
+            .with_context(|| format!("failed to interpolate `{key}`"))?;
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// Load a dotenv file
+    ///
+    /// - `KEY=value` lines, optionally prefixed with `export`
+    /// - empty lines and lines starting with `#` are ignored
+    /// - single-quoted values are not interpolated
+    pub fn load_file(&mut self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read env file {path:?}"))?;
+        for (num, line) in content.lines().enumerate() {
+            self.load_line(line)
+                .with_context(|| format!("invalid env file {path:?} line {}", num + 1))?;
+        }
+        Ok(())
+    }
+
+    fn load_line(&mut self, line: &str) -> Result<()> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line.split_once('=').context("missing `=`")?;
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(anyhow!("invalid variable name `{key}`"));
+        }
+        let value = value.trim();
+
+        if let Some(value) = value.strip_prefix('\'') {
+            let value = value.strip_suffix('\'').context("unterminated quote")?;
+            self.insert(key, value);
+            Ok(())
+        } else if let Some(value) = value.strip_prefix('"') {
+            let value = value.strip_suffix('"').context("unterminated quote")?;
+            self.set(key, &unescape(value))
+        } else {
+            /* strip inline comments */
+            let value = value.split_once(" #").map_or(value, |(v, _)| v.trim_end());
+            self.set(key, value)
+        }
+    }
+
+    /// Interpolate `${VAR}` and `${VAR:-default}` references
+    ///
+    /// Undefined variables are replaced by an empty string.
+    pub fn interpolate(&self, value: &str) -> Result<String> {
+        let mut ret = String::with_capacity(value.len());
+        let mut rest = value;
+
+        while let Some(pos) = rest.find('$') {
+            ret.push_str(&rest[..pos]);
+            rest = &rest[pos + 1..];
+            if let Some(tail) = rest.strip_prefix('$') {
+                ret.push('$');
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix('{') {
+                let end = tail.find('}').context("unterminated `${`")?;
+                let (name, default) = match tail[..end].split_once(":-") {
+                    Some((name, default)) => (name, Some(default)),
+                    None => (&tail[..end], None),
+                };
+                if name.is_empty() {
+                    return Err(anyhow!("empty variable name"));
+                }
+                match (self.get(name).filter(|v| !v.is_empty()), default) {
+                    (Some(value), _) => ret.push_str(&value),
+                    (None, Some(default)) => ret.push_str(default),
+                    (None, None) => tracing::warn!(name, "undefined variable"),
+                }
+                rest = &tail[end + 1..];
+            } else {
+                ret.push('$');
+            }
+        }
+        ret.push_str(rest);
+        Ok(ret)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+}
+
+/// Decode `\n`, `\"` and `\\` escapes of a double-quoted value
+fn unescape(value: &str) -> String {
+    let mut ret = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some('n')) => {
+                ret.push('\n');
+                chars.next();
+            }
+            ('\\', Some(&c @ ('"' | '\\'))) => {
+                ret.push(c);
+                chars.next();
+            }
+            _ => ret.push(c),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::MkTemp;
+
+    #[test]
+    fn interpolate() -> Result<()> {
+        let mut env = Environment::default();
+        env.insert("NAME", "world");
+        env.insert("EMPTY", "");
+
+        for (value, expected) in [
+            ("hello ${NAME}", "hello world"),
+            ("${NAME}${NAME}", "worldworld"),
+            ("${PPM_NO_SUCH_VAR:-default}", "default"),
+            ("${EMPTY:-default}", "default"),
+            ("${NAME:-default}", "world"),
+            ("${PPM_NO_SUCH_VAR}", ""),
+            ("$NAME $$ $${NAME} $", "$NAME $ ${NAME} $"),
+        ] {
+            assert_eq!(env.interpolate(value)?, expected, "{value}");
+        }
+        env.interpolate("${NAME").expect_err("unterminated");
+        env.interpolate("${}").expect_err("empty name");
+
+        let path = std::env::var("PATH")?;
+        assert_eq!(env.interpolate("${PATH}")?, path);
+        env.set("PATH", "/opt/bin:${PATH}")?;
+        assert_eq!(env.interpolate("${PATH}")?, format!("/opt/bin:{path}"));
+        Ok(())
+    }
+
+    #[test]
+    fn load_file() -> Result<()> {
+        let dir = MkTemp::dir("ppm-env")?;
+        let path = dir.join("test.env");
+        std::fs::write(
+            &path,
+            "# comment\n\
+            \n\
+            FIRST=first # inline comment\n\
+            export SECOND=\"${FIRST} \\\"second\\\"\"\n\
+            ESCAPED=\"a\\\\nb\\nc\"\n\
+            THIRD='${FIRST}'\n\
+            FOURTH = ${SECOND}\n",
+        )?;
+
+        let mut env = Environment::default();
+        env.load_file(&path)?;
+        assert_eq!(
+            env.iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("FIRST", "first"),
+                ("SECOND", "first \"second\""),
+                ("ESCAPED", "a\\nb\nc"),
+                ("THIRD", "${FIRST}"),
+                ("FOURTH", "first \"second\""),
+            ]
+        );
+
+        std::fs::write(&path, "INVALID\n")?;
+        let err = env.load_file(&path).expect_err("should fail");
+        assert!(format!("{err:#}").contains("line 1"), "{err:#}");
+        env.load_file(&dir.join("missing.env"))
+            .expect_err("missing file");
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-16T11:02:17
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Context, Result, anyhow, ensure};
+use libc::{WEXITSTATUS, WIFEXITED};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    process::Stdio,
+    time::Duration,
+};
+
+use super::{Command, Service};
+use crate::{
+    monitor::Monitor,
+    utils::{
+        serializers::human,
+        signal::{SIGKILL, Signal},
+    },
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_RETRIES: usize = 3;
+
+/// Service health check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    /// Probe to run
+    #[serde(flatten)]
+    pub probe: Probe,
+    /// Interval between two checks
+    #[serde(with = "human::duration", default = "default_interval")]
+    pub interval: Duration,
+    /// Probe timeout
+    #[serde(with = "human::duration", default = "default_timeout")]
+    pub timeout: Duration,
+    /// Consecutive failures before the service is considered unhealthy
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+    /// Initialization time, failures are not accounted during this period
+    #[serde(with = "human::duration", default)]
+    pub start_period: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Probe {
+    /// Run a command, healthy when exiting with `0`
+    Exec(Command),
+    /// Connect on a localhost TCP port
+    Tcp(u16),
+    /// HTTP GET on localhost, healthy on `2xx` and `3xx` replies
+    Http {
+        port: u16,
+        #[serde(default = "default_http_path")]
+        path: String,
+    },
+}
+
+/// Health status
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Health {
+    /// service started, no successful check yet
+    Starting,
+    /// last check succeeded
+    Healthy,
+    /// `retries` consecutive checks failed
+    Unhealthy,
+}
+
+fn default_interval() -> Duration {
+    DEFAULT_INTERVAL
+}
+
+fn default_timeout() -> Duration {
+    DEFAULT_TIMEOUT
+}
+
+fn default_retries() -> usize {
+    DEFAULT_RETRIES
+}
+
+fn default_http_path() -> String {
+    String::from("/")
+}
+
+impl HealthCheck {
+    pub fn new(probe: Probe) -> Self {
+        Self {
+            probe,
+            interval: DEFAULT_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            start_period: Duration::ZERO,
+        }
+    }
+
+    /// Run the probe
+    ///
+    /// Blocks for up to `timeout`, must not be called from the [Monitor] thread.
+    #[tracing::instrument(level = "DEBUG", fields(id = service.id, name = service.name), skip(self, service, monitor), err(level = "DEBUG"))]
+    pub fn check(&self, service: &Service, monitor: &Monitor) -> Result<()> {
+        match &self.probe {
+            Probe::Exec(command) => {
+                let mut cmd = service.make_command(command)?;
+                cmd.stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null());
+                let (pid, rx) = monitor.spawn_task(&mut cmd)?;
+                match rx.recv_timeout(self.timeout) {
+                    Ok(status) => {
+                        ensure!(
+                            WIFEXITED(status) && WEXITSTATUS(status) == 0,
+                            "probe failed (status: {status})"
+                        );
+                        Ok(())
+                    }
+                    Err(_) => {
+                        let _ = Signal::kill(Service::kill_target(pid), SIGKILL);
+                        Err(anyhow!("probe timeout"))
+                    }
+                }
+            }
+            Probe::Tcp(port) => {
+                TcpStream::connect_timeout(&localhost(*port), self.timeout)?;
+                Ok(())
+            }
+            Probe::Http { port, path } => {
+                let mut stream = TcpStream::connect_timeout(&localhost(*port), self.timeout)?;
+                stream.set_read_timeout(Some(self.timeout))?;
+                stream.set_write_timeout(Some(self.timeout))?;
+                let request = format!(
+                    "GET {path} HTTP/1.0\r\nHost: localhost:{port}\r\nConnection: close\r\n\r\n"
+                );
+                stream.write_all(request.as_bytes())?;
+
+                let mut line = String::new();
+                BufReader::new(stream).read_line(&mut line)?;
+                let code = line
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|code| code.parse::<u16>().ok())
+                    .with_context(|| format!("invalid HTTP reply: {:?}", line.trim_end()))?;
+                ensure!((200..400).contains(&code), "HTTP error {code}");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn localhost(port: u16) -> SocketAddr {
+    SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml_ng as yaml;
+    use std::net::TcpListener;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let hc: HealthCheck = yaml::from_str("tcp: 8080")?;
+        assert_eq!(hc, HealthCheck::new(Probe::Tcp(8080)));
+
+        let hc: HealthCheck =
+            yaml::from_str("{ http: { port: 80 }, interval: 10s, retries: 1, start_period: 1m }")?;
+        assert_eq!(
+            hc.probe,
+            Probe::Http {
+                port: 80,
+                path: "/".into()
+            }
+        );
+        assert_eq!(hc.interval, Duration::from_secs(10));
+        assert_eq!(hc.timeout, DEFAULT_TIMEOUT);
+        assert_eq!(hc.retries, 1);
+        assert_eq!(hc.start_period, Duration::from_mins(1));
+
+        let hc: HealthCheck = yaml::from_str("exec: { path: 'true' }")?;
+        assert_eq!(hc.probe, Probe::Exec(Command::new("true", [""; 0])));
+        assert_eq!(hc, yaml::from_str(&yaml::to_string(&hc)?)?);
+
+        yaml::from_str::<HealthCheck>("interval: 10s").expect_err("probe is mandatory");
+        Ok(())
+    }
+
+    #[test]
+    fn network_probes() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let service = Service::new("test", Command::new("true", [""; 0]));
+        let monitor = Monitor::default();
+
+        HealthCheck::new(Probe::Tcp(port)).check(&service, &monitor)?;
+        drop(listener.accept()?);
+
+        let server = std::thread::spawn(move || -> Result<()> {
+            for reply in [
+                "HTTP/1.0 200 OK\r\n\r\n",
+                "HTTP/1.0 503 Unavailable\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept()?;
+                let mut reader = BufReader::new(stream.try_clone()?);
+                let mut line = String::new();
+                while reader.read_line(&mut line)? > 2 {
+                    line.clear();
+                }
+                stream.write_all(reply.as_bytes())?;
+            }
+            Ok(())
+        });
+        let hc = HealthCheck::new(Probe::Http {
+            port,
+            path: "/health".into(),
+        });
+        hc.check(&service, &monitor)?;
+        hc.check(&service, &monitor)
+            .expect_err("503 should be unhealthy");
+        server.join().unwrap()?;
+
+        HealthCheck::new(Probe::Tcp(port))
+            .check(&service, &monitor)
+            .expect_err("listener is closed");
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T14:21:08
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use super::{Exit, Status};
+use crate::utils::serializers;
+
+/// Number of runs kept in a service history
+pub const MAX_HISTORY: usize = 50;
+
+/// What started a run
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTrigger {
+    /// daemon start or new service
+    Start,
+    /// `schedule`, including queued and caught up runs
+    Schedule,
+    /// `watch` event
+    Watch,
+    /// client request (ex: `ppm restart`)
+    Manual,
+    /// automatic restart (crash, health check, thresholds)
+    Restart,
+}
+
+/// A service run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Run {
+    pub trigger: RunTrigger,
+    #[serde(with = "serializers::instant")]
+    pub start: Instant,
+    /// Process termination, `None` while running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit: Option<Exit>,
+    /// [Status::Running], then [Status::Finished] or [Status::Crashed]
+    pub status: Status,
+    /// Run log file, in logger `per_run` mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<PathBuf>,
+}
+
+impl Run {
+    pub fn new(trigger: RunTrigger) -> Self {
+        Self {
+            trigger,
+            start: Instant::now(),
+            exit: None,
+            status: Status::Running,
+            log_file: None,
+        }
+    }
+
+    pub fn end(&self) -> Option<Instant> {
+        self.exit.as_ref().map(|exit| exit.time)
+    }
+
+    /// Run duration, up to now while running
+    pub fn duration(&self) -> Duration {
+        self.end()
+            .unwrap_or_else(Instant::now)
+            .saturating_duration_since(self.start)
+    }
+}
@@ -0,0 +1,175 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T16:21:48
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Result, anyhow, ensure};
+use libc::{WEXITSTATUS, WIFEXITED};
+use serde::{Deserialize, Serialize};
+use std::{process::Stdio, time::Duration};
+
+use super::{Command, Service};
+use crate::{
+    monitor::Monitor,
+    utils::{
+        serializers::human,
+        signal::{SIGKILL, Signal},
+    },
+};
+
+pub const HOOK_TIMEOUT_DEFAULT: Duration = Duration::from_secs(30);
+
+/// Command run on service lifecycle events (`pre_start`, `post_start`, `post_stop`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    /// Command to run
+    #[serde(flatten)]
+    pub command: Command,
+    /// Time to wait for the command to complete, it is killed afterwards
+    #[serde(
+        with = "human::duration",
+        skip_serializing_if = "is_default_hook_timeout",
+        default = "default_hook_timeout"
+    )]
+    pub timeout: Duration,
+}
+
+fn default_hook_timeout() -> Duration {
+    HOOK_TIMEOUT_DEFAULT
+}
+
+fn is_default_hook_timeout(timeout: &Duration) -> bool {
+    timeout == &HOOK_TIMEOUT_DEFAULT
+}
+
+impl Hook {
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            timeout: HOOK_TIMEOUT_DEFAULT,
+        }
+    }
+
+    /// Run the hook, succeeds when exiting with `0`
+    ///
+    /// Blocks for up to `timeout`, must not be called from the [Monitor] thread
+    /// while it is running.
+    #[tracing::instrument(fields(id = service.id, name = service.name), skip(self, service, monitor))]
+    pub fn run(&self, service: &Service, monitor: &Monitor) -> Result<()> {
+        let mut cmd = service.make_command(&self.command)?;
+        cmd.stdin(Stdio::null());
+        let (pid, rx) = monitor.spawn_task(&mut cmd)?;
+        match monitor.wait_task(pid, &rx, self.timeout) {
+            Some(status) => {
+                ensure!(
+                    WIFEXITED(status) && WEXITSTATUS(status) == 0,
+                    "hook failed (status: {status})"
+                );
+                Ok(())
+            }
+            None => {
+                let _ = Signal::kill(Service::kill_target(pid), SIGKILL);
+                Err(anyhow!("hook timeout"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{MkTemp, wait_for};
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let hook: Hook = yaml::from_str("{ path: sh, args: [-c, 'exit 0'] }")?;
+        assert_eq!(hook, Hook::new(Command::new("sh", ["-c", "exit 0"])));
+
+        let hook: Hook = yaml::from_str("{ path: 'true', timeout: 1m }")?;
+        assert_eq!(hook.timeout, Duration::from_mins(1));
+        assert_eq!(hook, yaml::from_str(&yaml::to_string(&hook)?)?);
+        Ok(())
+    }
+
+    #[test]
+    fn run() -> Result<()> {
+        let service = Service::new("test", Command::new("true", [""; 0]));
+        let monitor = Monitor::default();
+
+        Hook::new(Command::new("true", [""; 0])).run(&service, &monitor)?;
+        Hook::new(Command::new("false", [""; 0]))
+            .run(&service, &monitor)
+            .expect_err("hook should fail");
+
+        let mut hook = Hook::new(Command::new("sleep", ["10"]));
+        hook.timeout = Duration::from_millis(100);
+        let err = hook
+            .run(&service, &monitor)
+            .expect_err("hook should timeout");
+        assert!(err.to_string().contains("timeout"), "{err}");
+
+        /* hooks inherit the service environment */
+        let mut service = service;
+        service.command.env = Some(
+            [("FROM_SERVICE", "service"), ("OVERRIDE", "service")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+        let mut hook = Hook::new(Command::new(
+            "sh",
+            [
+                "-c",
+                r#"test "$FROM_SERVICE" = service -a "$OVERRIDE" = hook"#,
+            ],
+        ));
+        hook.run(&service, &monitor)
+            .expect_err("hook should not have `OVERRIDE`");
+        hook.command.env = Some([("OVERRIDE".to_string(), "hook".to_string())].into());
+        hook.run(&service, &monitor)?;
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn timeout_kills_group() -> Result<()> {
+        let dir = MkTemp::dir("ppm-hook")?;
+        let pid_file = dir.join("pid");
+        let service = Service::new("test", Command::new("true", [""; 0]));
+        let monitor = Monitor::default();
+
+        let script = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let mut hook = Hook::new(Command::new("sh", ["-c", &script]));
+        hook.timeout = Duration::from_millis(200);
+        hook.run(&service, &monitor)
+            .expect_err("hook should timeout");
+
+        let pid = std::fs::read_to_string(&pid_file)?;
+        /* killed, possibly left as a zombie until reaped */
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{}/stat", pid.trim()))
+                .is_ok_and(|stat| !stat.contains(") Z "))
+        };
+        wait_for!(!alive()).expect("grandchild not killed");
+        Ok(())
+    }
+}
@@ -21,14 +21,24 @@
 ** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
 */
 
-use crate::utils::serializers::{self, tabled::TDisplay};
+use crate::utils::{
+    serializers::{self, tabled::TDisplay},
+    signal::Signal,
+};
+use libc::{WCOREDUMP, WEXITSTATUS, WIFEXITED, WIFSIGNALED, WTERMSIG, c_int};
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, Instant};
+use std::{
+    collections::VecDeque,
+    fmt,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use tabled::{Tabled, derive::display};
 
 use super::{
-    Status,
-    tabled::{info_duration_str, info_status_str},
+    Health, RestartReason, Run, RunTrigger, Status,
+    history::MAX_HISTORY,
+    tabled::{info_duration_str, info_exit_str, info_health_str, info_status_str},
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Tabled)]
@@ -36,9 +46,13 @@ pub struct Info {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     #[tabled(display("display::option", ""))]
     pub pid: Option<libc::pid_t>,
+    /// Process group, kept once the process terminated to track orphans
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub pgid: Option<libc::pid_t>,
     #[tabled(display("TDisplay::to_string"))]
     pub active: bool,
-    #[tabled(display("info_status_str"))]
+    #[tabled(display("info_status_str", self))]
     pub status: Status,
     #[serde(
         with = "serializers::instant",
@@ -54,6 +68,10 @@ pub struct Info {
     )]
     #[tabled(display("info_duration_str", self), rename = "uptime")]
     pub end_time: Option<Instant>,
+    /// Last process termination
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(display("info_exit_str"), rename = "exit")]
+    pub last_exit: Option<Exit>,
     #[serde(default)]
     #[tabled(rename = "\u{21BA}")]
     pub restarts: usize,
@@ -64,19 +82,138 @@ pub struct Info {
     #[serde(default)]
     #[tabled(skip)]
     pub throttle: usize,
+    /// Health check status, if configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(display("info_health_str"))]
+    pub health: Option<Health>,
+    /// Consecutive failed health checks
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub health_failures: usize,
+    /// Last termination was caused by the OOM killer
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[tabled(skip)]
+    pub oom_killed: bool,
+    /// Number of OOM kills
+    #[serde(default, skip_serializing_if = "is_zero")]
+    #[tabled(skip)]
+    pub oom_kills: usize,
+    /// Last restart triggered by a resource threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(skip)]
+    pub restart_reason: Option<RestartReason>,
+    /// CPU usage is above `max_cpu` since
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub cpu_exceeded_since: Option<Instant>,
+    /// cgroup the process runs in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[tabled(skip)]
+    pub cgroup: Option<PathBuf>,
+    /// cgroup OOM kill counter
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub cgroup_oom_kills: u64,
+    /// Automatic restarts within the restart window
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub restart_times: VecDeque<Instant>,
+    /// Trigger of the next run, defaults to [RunTrigger::Restart]
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub trigger: Option<RunTrigger>,
+    /// Last runs, oldest first
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub history: VecDeque<Run>,
+    /// The daemon is stopping the current run
+    #[serde(skip)]
+    #[tabled(skip)]
+    pub stopping: bool,
+}
+
+/// Process termination details
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Exit {
+    /// Exit code, if the process exited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<c_int>,
+    /// Terminating signal, if the process was killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<Signal>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub core_dumped: bool,
+    #[serde(with = "serializers::instant")]
+    pub time: Instant,
+}
+
+impl Exit {
+    /// Decode a `waitpid` status, returns `None` if the process did not terminate
+    pub fn from_status(status: c_int) -> Option<Self> {
+        if WIFEXITED(status) {
+            Some(Self {
+                code: Some(WEXITSTATUS(status)),
+                signal: None,
+                core_dumped: false,
+                time: Instant::now(),
+            })
+        } else if WIFSIGNALED(status) {
+            Some(Self {
+                code: None,
+                signal: Some(Signal(WTERMSIG(status))),
+                core_dumped: WCOREDUMP(status),
+                time: Instant::now(),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (_, Some(signal)) => {
+                match signal.name() {
+                    Some(name) => write!(f, "{name}")?,
+                    None => write!(f, "signal {}", signal.0)?,
+                }
+                if self.core_dumped {
+                    write!(f, " (core)")?;
+                }
+                Ok(())
+            }
+            (Some(code), None) => write!(f, "{code}"),
+            (None, None) => Ok(()),
+        }
+    }
 }
 
 impl Default for Info {
     fn default() -> Self {
         Self {
             pid: None,
+            pgid: None,
             active: true,
             status: Status::Created,
             start_time: None,
             end_time: None,
+            last_exit: None,
             restarts: 0,
             crashed: 0,
             throttle: 0,
+            health: None,
+            health_failures: 0,
+            oom_killed: false,
+            oom_kills: 0,
+            restart_reason: None,
+            cpu_exceeded_since: None,
+            cgroup: None,
+            cgroup_oom_kills: 0,
+            restart_times: VecDeque::new(),
+            trigger: None,
+            history: VecDeque::new(),
+            stopping: false,
         }
     }
 }
@@ -84,13 +221,25 @@ impl Default for Info {
 impl Info {
     pub fn set_running(&mut self, pid: libc::pid_t) {
         match self.status {
-            Status::Created | Status::Finished | Status::Crashed => {
+            Status::Created | Status::Finished | Status::Crashed | Status::Failed => {
                 tracing::info!(pid, "{:?} -> {:?}", self.status, Status::Running);
                 self.pid = Some(pid);
+                self.pgid = Some(pid);
                 self.start_time = Some(std::time::Instant::now());
                 self.restarts += 1;
                 self.status = Status::Running;
                 self.end_time = None;
+                self.health = None;
+                self.health_failures = 0;
+                self.oom_killed = false;
+                self.cpu_exceeded_since = None;
+                self.stopping = false;
+
+                if self.history.len() >= MAX_HISTORY {
+                    self.history.pop_front();
+                }
+                let trigger = self.trigger.take().unwrap_or(RunTrigger::Restart);
+                self.history.push_back(Run::new(trigger));
             }
             Status::Running => {
                 self.pid = Some(pid);
@@ -111,6 +260,7 @@ impl Info {
                 self.pid = None;
                 self.status = Status::Finished;
                 self.end_time = Some(std::time::Instant::now());
+                self.end_run(Status::Finished);
             }
             Status::Finished => {}
             _ => tracing::warn!(
@@ -145,6 +295,7 @@ impl Info {
                 self.status = Status::Crashed;
                 self.crashed += 1;
                 self.end_time = Some(std::time::Instant::now());
+                self.end_run(Status::Crashed);
             }
             Status::Crashed => {}
             _ => tracing::warn!(
@@ -155,6 +306,128 @@ impl Info {
         }
     }
 
+    /// Record the current run outcome
+    fn end_run(&mut self, status: Status) {
+        if let Some(run) = self.history.back_mut()
+            && run.status == Status::Running
+        {
+            run.status = status;
+        }
+    }
+
+    /// Record the process termination status
+    pub fn set_exit(&mut self, status: c_int) {
+        if let Some(exit) = Exit::from_status(status) {
+            if let Some(run) = self.history.back_mut()
+                && run.exit.is_none()
+            {
+                run.exit = Some(exit.clone());
+            }
+            self.last_exit = Some(exit);
+        }
+    }
+
+    /// The service failed to start (ex: `pre_start` hook failure)
+    pub fn set_start_failed(&mut self) {
+        match self.status {
+            Status::Running | Status::Stopped => tracing::warn!(
+                status = ?self.status,
+                next = ?Status::Crashed,
+                "invalid process transition"
+            ),
+            _ => {
+                tracing::warn!("{:?} -> {:?}", self.status, Status::Crashed);
+                self.status = Status::Crashed;
+                self.crashed += 1;
+                self.end_time = Some(std::time::Instant::now());
+            }
+        }
+    }
+
+    pub fn set_failed(&mut self) {
+        match self.status {
+            Status::Finished | Status::Crashed => {
+                tracing::error!("{:?} -> {:?}", self.status, Status::Failed);
+                self.status = Status::Failed;
+                self.active = false;
+                self.restart_times.clear();
+            }
+            Status::Failed => {}
+            _ => tracing::warn!(
+                status = ?self.status,
+                next = ?Status::Failed,
+                "invalid process transition"
+            ),
+        }
+    }
+
+    /// Record an automatic restart
+    ///
+    /// Returns `false` and switches to [Status::Failed] when `max_restarts`
+    /// were already done within `window`.
+    pub fn register_restart(&mut self, max_restarts: usize, window: Duration) -> bool {
+        let now = Instant::now();
+        self.restart_times
+            .retain(|time| now.duration_since(*time) < window);
+        if self.restart_times.len() >= max_restarts {
+            self.set_failed();
+            false
+        } else {
+            self.restart_times.push_back(now);
+            true
+        }
+    }
+
+    /// Record the cgroup OOM kill counter, once the process got killed
+    ///
+    /// Returns `true` if the counter increased.
+    pub fn set_oom_kills(&mut self, oom_kills: u64) -> bool {
+        if oom_kills > self.cgroup_oom_kills {
+            tracing::warn!("process killed by the OOM killer");
+            self.cgroup_oom_kills = oom_kills;
+            self.oom_kills += 1;
+            self.oom_killed = true;
+        }
+        self.oom_killed
+    }
+
+    /// Record whether CPU usage is above `max_cpu`
+    ///
+    /// Returns `true` once it stayed above for `duration`.
+    pub fn set_cpu_exceeded(&mut self, exceeded: bool, duration: Duration) -> bool {
+        if exceeded {
+            self.cpu_exceeded_since
+                .get_or_insert_with(Instant::now)
+                .elapsed()
+                >= duration
+        } else {
+            self.cpu_exceeded_since = None;
+            false
+        }
+    }
+
+    /// Record a health check result
+    ///
+    /// - failures are not accounted during the `start_period`
+    /// - becomes [Health::Unhealthy] after `retries` consecutive failures
+    pub fn set_health(&mut self, healthy: bool, retries: usize, in_start_period: bool) -> Health {
+        let health = if healthy {
+            self.health_failures = 0;
+            Health::Healthy
+        } else if in_start_period {
+            self.health.unwrap_or(Health::Starting)
+        } else {
+            self.health_failures += 1;
+            if self.health_failures >= retries {
+                Health::Unhealthy
+            } else {
+                self.health.unwrap_or(Health::Starting)
+            }
+        };
+        self.health = Some(health);
+        health
+    }
+
     pub fn uptime(&self) -> Option<Duration> {
         if let Some(end_time) = self.end_time {
             self.start_time
@@ -170,6 +443,10 @@ impl Info {
     }
 }
 
+fn is_zero(value: &usize) -> bool {
+    value == &0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,4 +458,136 @@ mod tests {
         assert_eq!(data, serde_yaml_ng::to_string(&info).unwrap());
         assert_eq!(serde_yaml_ng::from_str::<Info>(data).unwrap(), info);
     }
+
+    #[test]
+    fn health() {
+        let mut info = Info::default();
+        assert_eq!(info.set_health(false, 2, true), Health::Starting);
+        assert_eq!(info.set_health(false, 2, false), Health::Starting);
+        assert_eq!(info.set_health(true, 2, false), Health::Healthy);
+        assert_eq!(info.set_health(false, 2, false), Health::Healthy);
+        assert_eq!(info.set_health(false, 2, false), Health::Unhealthy);
+        assert_eq!(info.health_failures, 2);
+    }
+
+    #[test]
+    fn exit() -> anyhow::Result<()> {
+        let mut info = Info::default();
+        info.set_exit(0x0100);
+        let exit = info.last_exit.clone().unwrap();
+        assert_eq!(exit.code, Some(1));
+        assert_eq!(exit.signal, None);
+
+        info.set_exit(libc::SIGSEGV | 0x80);
+        let exit = info.last_exit.clone().unwrap();
+        assert_eq!(exit.code, None);
+        assert_eq!(exit.signal, Some(Signal(libc::SIGSEGV)));
+        assert!(exit.core_dumped);
+
+        /* stopped processes are not terminated */
+        info.set_exit(0x137f);
+        assert_eq!(info.last_exit, Some(exit));
+
+        let data = serde_yaml_ng::to_string(&info.last_exit)?;
+        assert!(
+            data.contains("signal: SIGSEGV\ncore_dumped: true\n"),
+            "{data}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn history() {
+        let mut info = Info {
+            trigger: Some(RunTrigger::Schedule),
+            ..Default::default()
+        };
+        info.set_running(1);
+        assert_eq!(info.trigger, None);
+        assert_eq!(info.history.len(), 1);
+        assert_eq!(info.history[0].trigger, RunTrigger::Schedule);
+        assert_eq!(info.history[0].exit, None);
+
+        info.set_exit(0x0200);
+        info.set_crashed();
+        assert_eq!(info.history[0].exit, info.last_exit);
+        assert_eq!(info.history[0].exit.as_ref().unwrap().to_string(), "2");
+        assert_eq!(
+            info.history[0].end(),
+            info.last_exit.as_ref().map(|e| e.time)
+        );
+
+        for pid in 0..MAX_HISTORY {
+            info.set_running(pid as libc::pid_t);
+            info.set_exit(0);
+            info.set_finished();
+        }
+        assert_eq!(info.history.len(), MAX_HISTORY);
+        assert!(
+            info.history
+                .iter()
+                .all(|run| run.trigger == RunTrigger::Restart && run.exit.is_some())
+        );
+    }
+
+    #[test]
+    fn start_failed() {
+        let mut info = Info::default();
+        info.set_start_failed();
+        assert_eq!(info.status, Status::Crashed);
+        assert_eq!(info.crashed, 1);
+        assert!(info.end_time.is_some());
+
+        info.set_running(1);
+        info.set_start_failed();
+        assert_eq!(info.status, Status::Running);
+        assert_eq!(info.crashed, 1);
+    }
+
+    #[test]
+    fn oom_kills() {
+        let mut info = Info {
+            cgroup_oom_kills: 2,
+            ..Default::default()
+        };
+        info.set_running(1);
+        info.set_crashed();
+        assert!(!info.set_oom_kills(2));
+        assert!(info.set_oom_kills(3));
+        assert_eq!(info.oom_kills, 1);
+
+        info.set_running(2);
+        assert!(!info.oom_killed);
+        assert_eq!(info.oom_kills, 1);
+    }
+
+    #[test]
+    fn cpu_exceeded() {
+        let mut info = Info::default();
+        assert!(info.set_cpu_exceeded(true, Duration::ZERO));
+        assert!(!info.set_cpu_exceeded(false, Duration::ZERO));
+        assert!(info.cpu_exceeded_since.is_none());
+
+        assert!(!info.set_cpu_exceeded(true, Duration::from_mins(1)));
+        info.cpu_exceeded_since = Instant::now().checked_sub(Duration::from_mins(2));
+        assert!(info.set_cpu_exceeded(true, Duration::from_mins(1)));
+    }
+
+    #[test]
+    fn register_restart() {
+        let mut info = Info::default();
+        info.set_running(1);
+        info.set_crashed();
+        assert!(info.register_restart(2, Duration::from_secs(60)));
+        assert!(info.register_restart(2, Duration::from_secs(60)));
+        assert!(!info.register_restart(2, Duration::from_secs(60)));
+        assert_eq!(info.status, Status::Failed);
+        assert!(!info.active);
+
+        info.set_running(2);
+        assert_eq!(info.status, Status::Running);
+        info.set_crashed();
+        assert!(info.register_restart(1, Duration::ZERO));
+        assert!(info.register_restart(1, Duration::ZERO));
+    }
 }
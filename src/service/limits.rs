@@ -0,0 +1,368 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-16T17:05:21
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Result, anyhow};
+use bytesize::ByteSize;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::Error,
+    ser::{SerializeMap, SerializeStruct},
+};
+use std::collections::BTreeMap;
+
+use crate::utils::{libc::check, serializers::human};
+
+/* `rlim_t` is not a `u64` on every target */
+#[allow(clippy::unnecessary_cast)]
+pub const LIMIT_UNLIMITED: u64 = libc::RLIM_INFINITY as u64;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RawResource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type RawResource = libc::c_int;
+
+/// Process resource (see `setrlimit(2)`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Resource {
+    /// virtual memory size (bytes)
+    As,
+    /// core file size (bytes)
+    Core,
+    /// CPU time (seconds)
+    Cpu,
+    /// data segment size (bytes)
+    Data,
+    /// created files size (bytes)
+    Fsize,
+    /// locked memory (bytes)
+    Memlock,
+    /// open file descriptors
+    Nofile,
+    /// processes (threads) for the user
+    Nproc,
+    /// resident set size (bytes)
+    Rss,
+    /// stack size (bytes)
+    Stack,
+    /// file locks
+    #[cfg(target_os = "linux")]
+    Locks,
+    /// POSIX message queues size (bytes)
+    #[cfg(target_os = "linux")]
+    Msgqueue,
+    /// nice value ceiling
+    #[cfg(target_os = "linux")]
+    Nice,
+    /// real-time priority ceiling
+    #[cfg(target_os = "linux")]
+    Rtprio,
+    /// queued signals
+    #[cfg(target_os = "linux")]
+    Sigpending,
+}
+
+impl Resource {
+    /// Resource is a size in bytes
+    pub fn is_size(&self) -> bool {
+        match self {
+            Resource::As
+            | Resource::Core
+            | Resource::Data
+            | Resource::Fsize
+            | Resource::Memlock
+            | Resource::Rss
+            | Resource::Stack => true,
+            #[cfg(target_os = "linux")]
+            Resource::Msgqueue => true,
+            _ => false,
+        }
+    }
+
+    /// Get current process limit
+    #[allow(clippy::unnecessary_cast)]
+    pub fn get(&self) -> Result<Limit> {
+        let mut rlim = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        check(unsafe { libc::getrlimit(self.raw(), &mut rlim) })?;
+        Ok(Limit::new(rlim.rlim_cur as u64, Some(rlim.rlim_max as u64)))
+    }
+
+    /// Set current process limit, an unset hard limit is kept as-is
+    pub fn set(&self, limit: &Limit) -> Result<()> {
+        let hard = match limit.hard {
+            Some(hard) => hard,
+            None => self.get()?.hard.unwrap_or(LIMIT_UNLIMITED),
+        };
+        if limit.soft > hard {
+            return Err(anyhow!("soft limit exceeds hard limit ({hard})"));
+        }
+        let rlim = libc::rlimit {
+            rlim_cur: limit.soft as libc::rlim_t,
+            rlim_max: hard as libc::rlim_t,
+        };
+        check(unsafe { libc::setrlimit(self.raw(), &rlim) })
+    }
+
+    fn raw(&self) -> RawResource {
+        match self {
+            Resource::As => libc::RLIMIT_AS,
+            Resource::Core => libc::RLIMIT_CORE,
+            Resource::Cpu => libc::RLIMIT_CPU,
+            Resource::Data => libc::RLIMIT_DATA,
+            Resource::Fsize => libc::RLIMIT_FSIZE,
+            Resource::Memlock => libc::RLIMIT_MEMLOCK,
+            Resource::Nofile => libc::RLIMIT_NOFILE,
+            Resource::Nproc => libc::RLIMIT_NPROC,
+            Resource::Rss => libc::RLIMIT_RSS,
+            Resource::Stack => libc::RLIMIT_STACK,
+            #[cfg(target_os = "linux")]
+            Resource::Locks => libc::RLIMIT_LOCKS,
+            #[cfg(target_os = "linux")]
+            Resource::Msgqueue => libc::RLIMIT_MSGQUEUE,
+            #[cfg(target_os = "linux")]
+            Resource::Nice => libc::RLIMIT_NICE,
+            #[cfg(target_os = "linux")]
+            Resource::Rtprio => libc::RLIMIT_RTPRIO,
+            #[cfg(target_os = "linux")]
+            Resource::Sigpending => libc::RLIMIT_SIGPENDING,
+        }
+    }
+
+    fn name(&self) -> String {
+        format!("{self:?}").to_lowercase()
+    }
+}
+
+/// Soft and hard limits, [LIMIT_UNLIMITED] for no limit
+///
+/// An unset `hard` limit keeps the current one when applied
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub soft: u64,
+    pub hard: Option<u64>,
+}
+
+impl Limit {
+    pub fn new(soft: u64, hard: Option<u64>) -> Self {
+        Self { soft, hard }
+    }
+}
+
+/// Service resource limits
+///
+/// - a single value sets both soft and hard limits
+/// - `hard` is left unchanged when only `soft` is given, it is then serialized
+///   as `soft` only since the effective value depends on the launching process
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits(pub BTreeMap<Resource, Limit>);
+
+impl Limits {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Apply limits on current process
+    pub fn apply(&self) -> Result<()> {
+        for (resource, limit) in self.0.iter() {
+            resource
+                .set(limit)
+                .map_err(|err| anyhow!("failed to set `{}` limit: {err}", resource.name()))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Number(u64),
+    Text(String),
+}
+
+impl RawValue {
+    /// Size units are only accepted for size resources
+    fn parse(self, resource: &Resource) -> Result<u64, String> {
+        match self {
+            RawValue::Number(value) => Ok(value),
+            RawValue::Text(text) => match text.to_lowercase().as_str() {
+                "unlimited" | "infinity" => Ok(LIMIT_UNLIMITED),
+                _ if resource.is_size() => text.parse::<ByteSize>().map(|size| size.as_u64()),
+                _ => text.parse::<u64>().map_err(|_| {
+                    format!(
+                        "invalid `{}` limit `{text}`, expected a number",
+                        resource.name()
+                    )
+                }),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LimitDef {
+    Value(RawValue),
+    Range {
+        soft: RawValue,
+        #[serde(default)]
+        hard: Option<RawValue>,
+    },
+}
+
+impl<'de> Deserialize<'de> for Limits {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let defs = BTreeMap::<Resource, LimitDef>::deserialize(deserializer)?;
+        let mut limits = BTreeMap::new();
+        for (resource, def) in defs.into_iter() {
+            let limit = match def {
+                LimitDef::Value(value) => {
+                    let value = value.parse(&resource).map_err(Error::custom)?;
+                    Limit::new(value, Some(value))
+                }
+                LimitDef::Range { soft, hard } => {
+                    let soft = soft.parse(&resource).map_err(Error::custom)?;
+                    let hard = hard
+                        .map(|hard| hard.parse(&resource).map_err(Error::custom))
+                        .transpose()?;
+                    Limit::new(soft, hard)
+                }
+            };
+            if limit.hard.is_some_and(|hard| limit.soft > hard) {
+                return Err(Error::custom(format!(
+                    "invalid `{}` limit, soft limit exceeds hard limit",
+                    resource.name()
+                )));
+            }
+            limits.insert(resource, limit);
+        }
+        Ok(Self(limits))
+    }
+}
+
+struct ValueRef<'a>(&'a Resource, &'a u64);
+
+impl Serialize for ValueRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.1 == &LIMIT_UNLIMITED {
+            "unlimited".serialize(serializer)
+        } else if self.0.is_size() {
+            human::size::serialize(self.1, serializer)
+        } else {
+            self.1.serialize(serializer)
+        }
+    }
+}
+
+struct LimitRef<'a>(&'a Resource, &'a Limit);
+
+impl Serialize for LimitRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.1.hard {
+            Some(hard) if hard == &self.1.soft => {
+                ValueRef(self.0, &self.1.soft).serialize(serializer)
+            }
+            Some(hard) => {
+                let mut state = serializer.serialize_struct("Limit", 2)?;
+                state.serialize_field("soft", &ValueRef(self.0, &self.1.soft))?;
+                state.serialize_field("hard", &ValueRef(self.0, hard))?;
+                state.end()
+            }
+            None => {
+                let mut state = serializer.serialize_struct("Limit", 1)?;
+                state.serialize_field("soft", &ValueRef(self.0, &self.1.soft))?;
+                state.end()
+            }
+        }
+    }
+}
+
+impl Serialize for Limits {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (resource, limit) in self.0.iter() {
+            map.serialize_entry(resource, &LimitRef(resource, limit))?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let limits: Limits = yaml::from_str(
+            "{ nofile: 1024, core: unlimited, as: 1GiB, nproc: { soft: 10, hard: 20 }, stack: { soft: 1MiB } }",
+        )?;
+        assert_eq!(limits.0[&Resource::Nofile], Limit::new(1024, Some(1024)));
+        assert_eq!(
+            limits.0[&Resource::Core],
+            Limit::new(LIMIT_UNLIMITED, Some(LIMIT_UNLIMITED))
+        );
+        assert_eq!(limits.0[&Resource::As], Limit::new(1 << 30, Some(1 << 30)));
+        assert_eq!(limits.0[&Resource::Nproc], Limit::new(10, Some(20)));
+        assert_eq!(limits.0[&Resource::Stack], Limit::new(1 << 20, None));
+
+        let data = yaml::to_string(&limits)?;
+        assert!(data.contains("as: 1GiB\n"), "{data}");
+        assert!(data.contains("core: unlimited\n"), "{data}");
+        assert!(data.contains("nofile: 1024\n"), "{data}");
+        assert_eq!(yaml::from_str::<Limits>(&data)?, limits);
+        assert_eq!(
+            serde_json::from_str::<Limits>(&serde_json::to_string(&limits)?)?,
+            limits
+        );
+
+        let err = yaml::from_str::<Limits>("nofile: { soft: 20, hard: 10 }")
+            .expect_err("soft > hard should fail");
+        assert!(err.to_string().contains("`nofile`"), "{err}");
+        yaml::from_str::<Limits>("nofile: lots").expect_err("invalid value");
+        for data in ["nofile: 1k", "cpu: 1GiB", "nproc: { soft: 2MiB }"] {
+            let err = yaml::from_str::<Limits>(data).expect_err("size unit on a count");
+            assert!(err.to_string().contains("expected a number"), "{err}");
+        }
+        assert_eq!(
+            yaml::from_str::<Limits>("nofile: '4096'")?.0[&Resource::Nofile],
+            Limit::new(4096, Some(4096))
+        );
+        yaml::from_str::<Limits>("unknown: 12").expect_err("invalid resource");
+        Ok(())
+    }
+}
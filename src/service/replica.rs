@@ -0,0 +1,62 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T09:41:05
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Replica (instance) of a service
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replica {
+    /// Parent service name
+    pub parent: String,
+    /// Instance index, `0` being the parent service itself
+    pub index: usize,
+}
+
+impl Replica {
+    /// Replica service name
+    pub fn name(&self) -> String {
+        format!("{}.{}", self.parent, self.index)
+    }
+}
+
+pub(super) fn default_instances() -> AtomicUsize {
+    AtomicUsize::new(1)
+}
+
+pub(super) fn is_single_instance(instances: &AtomicUsize) -> bool {
+    instances.load(Ordering::Relaxed) == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name() {
+        let replica = Replica {
+            parent: "worker".into(),
+            index: 2,
+        };
+        assert_eq!(replica.name(), "worker.2");
+    }
+}
@@ -0,0 +1,117 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-16T15:02:48
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use super::Status;
+
+pub const RESTART_WINDOW_DEFAULT: Duration = Duration::from_secs(60);
+
+/// Service restart policy, applied when the process terminates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// always restart the process
+    Always,
+    /// restart the process when it crashed
+    #[default]
+    OnFailure,
+    /// never restart the process
+    Never,
+    /// restart the process, unless it was stopped by the daemon
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    /// Check whether a terminated process should be restarted
+    ///
+    /// - `stopped` is set when the daemon stopped the process
+    pub fn should_restart(&self, status: Status, stopped: bool) -> bool {
+        match self {
+            RestartPolicy::Always => matches!(status, Status::Finished | Status::Crashed),
+            RestartPolicy::OnFailure => status == Status::Crashed,
+            RestartPolicy::Never => false,
+            RestartPolicy::UnlessStopped => match status {
+                Status::Crashed => true,
+                Status::Finished => !stopped,
+                _ => false,
+            },
+        }
+    }
+}
+
+pub(super) fn is_default_restart_policy(policy: &RestartPolicy) -> bool {
+    policy == &RestartPolicy::default()
+}
+
+pub(super) fn default_restart_window() -> Duration {
+    RESTART_WINDOW_DEFAULT
+}
+
+pub(super) fn is_default_restart_window(window: &Duration) -> bool {
+    window == &RESTART_WINDOW_DEFAULT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn should_restart() {
+        use RestartPolicy::*;
+        use Status::*;
+
+        for (policy, status, stopped, expected) in [
+            (Always, Finished, false, true),
+            (Always, Finished, true, true),
+            (Always, Crashed, false, true),
+            (OnFailure, Finished, false, false),
+            (OnFailure, Crashed, false, true),
+            (Never, Crashed, false, false),
+            (UnlessStopped, Finished, false, true),
+            (UnlessStopped, Finished, true, false),
+            (UnlessStopped, Crashed, false, true),
+            (Always, Running, false, false),
+        ] {
+            assert_eq!(
+                policy.should_restart(status, stopped),
+                expected,
+                "{policy:?} {status:?} stopped:{stopped}"
+            );
+        }
+    }
+
+    #[test]
+    fn serde() {
+        assert_eq!(
+            yaml::from_str::<RestartPolicy>("unless-stopped").unwrap(),
+            RestartPolicy::UnlessStopped
+        );
+        assert_eq!(
+            yaml::to_string(&RestartPolicy::OnFailure).unwrap(),
+            "on-failure\n"
+        );
+    }
+}
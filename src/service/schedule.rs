@@ -0,0 +1,379 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T12:20:44
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use anyhow::{Result, anyhow, bail};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::{BuildHasher, RandomState},
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::utils::serializers::human;
+
+/// Service schedule
+///
+/// Can be written as a plain cron expression, or as a map with one of `cron`,
+/// `every` or `at`, and optional `timezone` and `jitter`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ScheduleDef", into = "ScheduleDef")]
+pub struct Schedule {
+    pub trigger: Trigger,
+    /// Time zone `cron` and `at` are evaluated in, defaults to local time
+    pub timezone: Option<Tz>,
+    /// Random delay added to each run
+    pub jitter: Option<Duration>,
+}
+
+/// What triggers a scheduled run
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::large_enum_variant)] /* one per service, mostly `Cron` */
+pub enum Trigger {
+    Cron(Cron),
+    /// Fixed interval between runs
+    Every {
+        interval: Duration,
+        from: IntervalFrom,
+    },
+    /// Single run
+    At(DateTime<FixedOffset>),
+}
+
+/// Reference point of an `every` interval
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntervalFrom {
+    /// previous run start
+    #[default]
+    Start,
+    /// previous run completion
+    Completion,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ScheduleDef {
+    Cron(String),
+    Full {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cron: Option<String>,
+        #[serde(
+            with = "human::duration",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        every: Option<Duration>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from: Option<IntervalFrom>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        at: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+        #[serde(
+            with = "human::duration",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        jitter: Option<Duration>,
+    },
+}
+
+impl Schedule {
+    pub fn new(trigger: Trigger) -> Self {
+        Self {
+            trigger,
+            timezone: None,
+            jitter: None,
+        }
+    }
+
+    /// Next occurrence after `start`, DST transitions are handled in `timezone`
+    ///
+    /// Returns `None` once a one-shot schedule is over.
+    pub fn find_next_occurrence(
+        &self,
+        start: &DateTime<Local>,
+        inclusive: bool,
+    ) -> Result<Option<DateTime<Local>>> {
+        match &self.trigger {
+            Trigger::Cron(cron) => match self.timezone {
+                Some(tz) => Ok(Some(
+                    cron.find_next_occurrence(&start.with_timezone(&tz), inclusive)?
+                        .with_timezone(&Local),
+                )),
+                None => Ok(Some(cron.find_next_occurrence(start, inclusive)?)),
+            },
+            Trigger::Every { interval, .. } => Ok(Some(*start + *interval)),
+            Trigger::At(at) => {
+                let at = at.with_timezone(&Local);
+                Ok((at > *start || (inclusive && at == *start)).then_some(at))
+            }
+        }
+    }
+
+    /// Whether the next run is scheduled once the previous one completed
+    pub fn is_from_completion(&self) -> bool {
+        matches!(
+            self.trigger,
+            Trigger::Every {
+                from: IntervalFrom::Completion,
+                ..
+            }
+        )
+    }
+
+    /// Random delay in `[0, jitter)`
+    pub fn jitter_delay(&self) -> Duration {
+        match self.jitter {
+            Some(jitter) if !jitter.is_zero() => {
+                let random = RandomState::new().hash_one(std::time::Instant::now());
+                Duration::from_nanos(random % jitter.as_nanos().min(u64::MAX as u128) as u64)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Cron::from_str(s)
+            .map(|cron| Self::new(Trigger::Cron(cron)))
+            .map_err(|err| anyhow!("invalid schedule `{s}`: {err}"))
+    }
+}
+
+/// Parse an `at` date, local to `timezone` when it has no offset
+fn parse_at(at: &str, timezone: Option<Tz>) -> Result<DateTime<FixedOffset>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(at) {
+        return Ok(date);
+    }
+    let naive = [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(at, fmt).ok())
+    .ok_or_else(|| anyhow!("invalid date `{at}`"))?;
+    let date = match timezone {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|d| d.fixed_offset()),
+        None => Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|d| d.fixed_offset()),
+    };
+    date.ok_or_else(|| anyhow!("date `{at}` does not exist in this time zone"))
+}
+
+impl TryFrom<ScheduleDef> for Schedule {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ScheduleDef) -> Result<Self> {
+        match value {
+            ScheduleDef::Cron(cron) => cron.parse(),
+            ScheduleDef::Full {
+                cron,
+                every,
+                from,
+                at,
+                timezone,
+                jitter,
+            } => {
+                let timezone = timezone
+                    .map(|tz| Tz::from_str(&tz).map_err(|_| anyhow!("unknown timezone `{tz}`")))
+                    .transpose()?;
+                let trigger = match (cron, every, at) {
+                    (Some(cron), None, None) => cron.parse::<Schedule>()?.trigger,
+                    (None, Some(interval), None) => {
+                        if interval.is_zero() {
+                            bail!("`every` must not be zero");
+                        }
+                        Trigger::Every {
+                            interval,
+                            from: from.unwrap_or_default(),
+                        }
+                    }
+                    (None, None, Some(at)) => Trigger::At(parse_at(&at, timezone)?),
+                    _ => bail!("schedule requires exactly one of `cron`, `every` or `at`"),
+                };
+                if from.is_some() && !matches!(trigger, Trigger::Every { .. }) {
+                    bail!("`from` only applies to `every` schedules");
+                }
+                Ok(Self {
+                    trigger,
+                    timezone,
+                    jitter,
+                })
+            }
+        }
+    }
+}
+
+impl From<Schedule> for ScheduleDef {
+    fn from(value: Schedule) -> Self {
+        let timezone = value.timezone.map(|tz| tz.name().to_string());
+        match value.trigger {
+            Trigger::Cron(cron) if timezone.is_none() && value.jitter.is_none() => {
+                ScheduleDef::Cron(cron.to_string())
+            }
+            trigger => {
+                let (cron, every, from, at) = match trigger {
+                    Trigger::Cron(cron) => (Some(cron.to_string()), None, None, None),
+                    Trigger::Every { interval, from } => (
+                        None,
+                        Some(interval),
+                        (from != IntervalFrom::default()).then_some(from),
+                        None,
+                    ),
+                    Trigger::At(at) => (None, None, None, Some(at.to_rfc3339())),
+                };
+                ScheduleDef::Full {
+                    cron,
+                    every,
+                    from,
+                    at,
+                    timezone,
+                    jitter: value.jitter,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let schedule: Schedule = yaml::from_str("'0 9 * * *'")?;
+        assert_eq!(schedule.timezone, None);
+        assert_eq!(yaml::to_string(&schedule)?, "0 9 * * *\n");
+
+        let schedule: Schedule = yaml::from_str("{ cron: '0 9 * * *', timezone: Europe/Paris }")?;
+        assert_eq!(schedule.timezone, Some(chrono_tz::Europe::Paris));
+        assert_eq!(schedule, yaml::from_str(&yaml::to_string(&schedule)?)?);
+
+        let err = yaml::from_str::<Schedule>("{ cron: '0 9 * * *', timezone: Mars/Olympus }")
+            .expect_err("unknown timezone");
+        assert!(err.to_string().contains("Mars/Olympus"), "{err}");
+        yaml::from_str::<Schedule>("'not a cron'").expect_err("invalid cron");
+
+        let schedule: Schedule = yaml::from_str("{ every: 90s, from: completion, jitter: 5s }")?;
+        assert_eq!(
+            schedule.trigger,
+            Trigger::Every {
+                interval: Duration::from_secs(90),
+                from: IntervalFrom::Completion
+            }
+        );
+        assert!(schedule.is_from_completion());
+        assert_eq!(schedule.jitter, Some(Duration::from_secs(5)));
+        assert_eq!(schedule, yaml::from_str(&yaml::to_string(&schedule)?)?);
+
+        let schedule: Schedule =
+            yaml::from_str("{ at: '2026-11-01T03:00', timezone: Europe/Paris }")?;
+        assert_eq!(
+            schedule.trigger,
+            Trigger::At(DateTime::parse_from_rfc3339("2026-11-01T03:00:00+01:00")?)
+        );
+        assert_eq!(schedule, yaml::from_str(&yaml::to_string(&schedule)?)?);
+
+        for (def, err) in [
+            ("{ cron: '0 9 * * *', every: 1m }", "exactly one"),
+            ("{ timezone: Europe/Paris }", "exactly one"),
+            ("{ every: 0s }", "zero"),
+            ("{ at: '2026-11-01T03:00', from: start }", "`from`"),
+            ("{ at: tomorrow }", "tomorrow"),
+        ] {
+            let ret = yaml::from_str::<Schedule>(def).expect_err(def);
+            assert!(ret.to_string().contains(err), "{def}: {ret}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn triggers() -> Result<()> {
+        let now = Local::now();
+        let schedule: Schedule = yaml::from_str("{ every: 90s }")?;
+        assert_eq!(
+            schedule.find_next_occurrence(&now, false)?,
+            Some(now + Duration::from_secs(90))
+        );
+        assert!(!schedule.is_from_completion());
+        assert_eq!(schedule.jitter_delay(), Duration::ZERO);
+
+        let at = now + Duration::from_secs(60);
+        let schedule = Schedule::new(Trigger::At(at.fixed_offset()));
+        assert_eq!(schedule.find_next_occurrence(&now, false)?, Some(at));
+        assert_eq!(schedule.find_next_occurrence(&at, true)?, Some(at));
+        assert_eq!(schedule.find_next_occurrence(&at, false)?, None);
+
+        let schedule: Schedule = yaml::from_str("{ cron: '0 9 * * *', jitter: 10s }")?;
+        for _ in 0..10 {
+            assert!(schedule.jitter_delay() < Duration::from_secs(10));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn timezone() -> Result<()> {
+        let schedule: Schedule = yaml::from_str("{ cron: '0 9 * * *', timezone: Europe/Paris }")?;
+        let utc = |y, m, d, h| {
+            Utc.from_utc_datetime(
+                &NaiveDate::from_ymd_opt(y, m, d)
+                    .unwrap()
+                    .and_hms_opt(h, 0, 0)
+                    .unwrap(),
+            )
+        };
+
+        /* CEST (UTC+2) then CET (UTC+1), DST ends on 2026-10-25 */
+        let next = schedule.find_next_occurrence(&utc(2026, 10, 24, 0).into(), false)?;
+        assert_eq!(next, Some(utc(2026, 10, 24, 7).into()));
+        let next = schedule.find_next_occurrence(&next.unwrap(), false)?;
+        assert_eq!(next, Some(utc(2026, 10, 25, 8).into()));
+
+        /* 02:30 does not exist on 2026-03-29 */
+        let schedule: Schedule = yaml::from_str("{ cron: '30 2 * * *', timezone: Europe/Paris }")?;
+        let next = schedule
+            .find_next_occurrence(&utc(2026, 3, 28, 12).into(), false)?
+            .unwrap();
+        assert!(
+            next >= utc(2026, 3, 29, 0) && next < utc(2026, 3, 30, 0),
+            "{next}"
+        );
+        Ok(())
+    }
+}
@@ -35,6 +35,8 @@ pub enum Status {
     Stopped,
     /// process has finished with a `!= 0` status code
     Crashed,
+    /// restart limit reached, process won't be restarted
+    Failed,
 }
 
 #[cfg(test)]
@@ -0,0 +1,97 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-16T14:20:05
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::utils::{
+    serializers::human,
+    signal::{SIGKILL, SIGTERM, Signal},
+};
+
+pub const STOP_SIGNAL_DEFAULT: Signal = SIGTERM;
+pub const STOP_TIMEOUT_DEFAULT: Duration = Duration::from_secs(5);
+
+/// Stop sequence step: a signal and the time to wait for the process to end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StopStep {
+    pub signal: Signal,
+    #[serde(with = "human::duration")]
+    pub timeout: Duration,
+}
+
+impl StopStep {
+    pub fn new(signal: Signal, timeout: Duration) -> Self {
+        Self { signal, timeout }
+    }
+}
+
+pub(super) fn default_stop_signal() -> Signal {
+    STOP_SIGNAL_DEFAULT
+}
+
+pub(super) fn is_default_stop_signal(signal: &Signal) -> bool {
+    signal == &STOP_SIGNAL_DEFAULT
+}
+
+pub(super) fn default_stop_timeout() -> Duration {
+    STOP_TIMEOUT_DEFAULT
+}
+
+pub(super) fn is_default_stop_timeout(timeout: &Duration) -> bool {
+    timeout == &STOP_TIMEOUT_DEFAULT
+}
+
+/// Escalation applied once `stop_timeout` expired
+pub(super) fn default_stop_escalation() -> Vec<StopStep> {
+    vec![StopStep::new(SIGKILL, Duration::from_secs(10))]
+}
+
+pub(super) fn is_default_stop_escalation(steps: &Vec<StopStep>) -> bool {
+    steps == &default_stop_escalation()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let steps: Vec<StopStep> =
+            yaml::from_str("[ { signal: SIGTERM, timeout: 1m }, { signal: KILL, timeout: 5s } ]")?;
+        assert_eq!(
+            steps,
+            vec![
+                StopStep::new(SIGTERM, Duration::from_mins(1)),
+                StopStep::new(SIGKILL, Duration::from_secs(5))
+            ]
+        );
+        assert_eq!(
+            steps,
+            yaml::from_str::<Vec<StopStep>>(&yaml::to_string(&steps)?)?
+        );
+        Ok(())
+    }
+}
@@ -23,12 +23,16 @@
 
 use std::time::{Duration, Instant};
 
-use super::{Info, Status};
+use super::{Exit, Health, Info, Status};
 use crate::utils::{IS_OUT_COLORED, serializers::tabled::TDisplay};
 use colored::Colorize;
 
-pub fn info_status_str(status: &Status) -> String {
-    let str = format!("{status:?}");
+pub fn info_status_str(status: &Status, info: &Info) -> String {
+    let str = if info.oom_killed && status == &Status::Crashed {
+        format!("{status:?} (OOM)")
+    } else {
+        format!("{status:?}")
+    };
     if IS_OUT_COLORED.get() {
         match status {
             Status::Created => str.bright_black().to_string(),
@@ -36,12 +40,31 @@ pub fn info_status_str(status: &Status) -> String {
             Status::Finished => str.bright_black().to_string(),
             Status::Stopped => str.bright_yellow().to_string(),
             Status::Crashed => str.red().to_string(),
+            Status::Failed => str.bright_red().bold().to_string(),
+        }
+    } else {
+        str
+    }
+}
+
+pub fn info_health_str(health: &Option<Health>) -> String {
+    let str = health.map(|h| format!("{h:?}")).unwrap_or_default();
+    if IS_OUT_COLORED.get() {
+        match health {
+            Some(Health::Starting) => str.bright_yellow().to_string(),
+            Some(Health::Healthy) => str.green().to_string(),
+            Some(Health::Unhealthy) => str.red().to_string(),
+            None => str,
         }
     } else {
         str
     }
 }
 
+pub fn info_exit_str(exit: &Option<Exit>) -> String {
+    exit.as_ref().map(Exit::to_string).unwrap_or_default()
+}
+
 pub fn info_duration_str(_: &Option<Instant>, info: &Info) -> String {
     info.uptime()
         .map(|d| Duration::from_secs(d.as_secs()).to_string())
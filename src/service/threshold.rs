@@ -0,0 +1,70 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-17T09:41:26
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::utils::serializers::human;
+
+/// Sustained CPU usage threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CpuThreshold {
+    /// CPU usage in `%` (`100` for a full CPU)
+    pub usage: f32,
+    /// Time the usage must stay above the threshold
+    #[serde(with = "human::duration", default)]
+    pub duration: Duration,
+}
+
+/// Resource threshold that triggered an automatic restart
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartReason {
+    /// memory usage went above `max_memory`
+    MaxMemory,
+    /// CPU usage stayed above `max_cpu` for its whole `duration`
+    MaxCpu,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use serde_yaml_ng as yaml;
+
+    #[test]
+    fn serde() -> Result<()> {
+        let threshold: CpuThreshold = yaml::from_str("{ usage: 90, duration: 5m }")?;
+        assert_eq!(
+            threshold,
+            CpuThreshold {
+                usage: 90.0,
+                duration: Duration::from_mins(5)
+            }
+        );
+        let threshold: CpuThreshold = yaml::from_str("usage: 50")?;
+        assert_eq!(threshold.duration, Duration::ZERO);
+        assert_eq!(yaml::to_string(&RestartReason::MaxMemory)?, "max_memory\n");
+        Ok(())
+    }
+}
@@ -31,7 +31,8 @@ use std::{
     sync::LazyLock,
 };
 
-use crate::utils::{GlobSet, serde_utils::OneOrManyWrapper};
+use super::Hook;
+use crate::utils::{GlobSet, serde_utils::OneOrManyWrapper, signal::Signal};
 
 static DEFAULT_EXCLUDE: LazyLock<GlobSet> =
     LazyLock::new(|| GlobSet::try_from([".?*", "**/{build,target}*", "*.o"]).unwrap());
@@ -48,6 +49,54 @@ pub struct Watch {
     pub paths: Vec<PathBuf>,
     /// Maximum depth
     pub max_depth: usize,
+    /// Action taken on changes
+    pub on_change: OnChange,
+}
+
+/// Action taken when a watched path changes
+///
+/// Written as `restart`, `{ signal: SIGHUP }` or `{ run: <command> }`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "OnChangeDef", into = "OnChangeDef")]
+pub enum OnChange {
+    /// restart the service
+    #[default]
+    Restart,
+    /// send a signal to the service (ex: `SIGHUP` to reload)
+    Signal(Signal),
+    /// run a one-shot command (ex: a build), restart the service if it succeeds
+    Run(Hook),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum OnChangeDef {
+    Action(String),
+    Signal { signal: Signal },
+    Run { run: Hook },
+}
+
+impl TryFrom<OnChangeDef> for OnChange {
+    type Error = String;
+
+    fn try_from(value: OnChangeDef) -> Result<Self, Self::Error> {
+        match value {
+            OnChangeDef::Action(action) if action == "restart" => Ok(Self::Restart),
+            OnChangeDef::Action(action) => Err(format!("unknown `on_change` action `{action}`")),
+            OnChangeDef::Signal { signal } => Ok(Self::Signal(signal)),
+            OnChangeDef::Run { run } => Ok(Self::Run(run)),
+        }
+    }
+}
+
+impl From<OnChange> for OnChangeDef {
+    fn from(value: OnChange) -> Self {
+        match value {
+            OnChange::Restart => Self::Action(String::from("restart")),
+            OnChange::Signal(signal) => Self::Signal { signal },
+            OnChange::Run(run) => Self::Run { run },
+        }
+    }
 }
 
 impl Default for Watch {
@@ -57,6 +106,7 @@ impl Default for Watch {
             include: Default::default(),
             paths: Default::default(),
             max_depth: DEFAULT_MAX_DEPTH,
+            on_change: OnChange::default(),
         }
     }
 }
@@ -86,6 +136,9 @@ impl fmt::Debug for Watch {
         if self.max_depth != DEFAULT_MAX_DEPTH {
             f.field("max_depth", &self.max_depth);
         }
+        if self.on_change != OnChange::default() {
+            f.field("on_change", &self.on_change);
+        }
         f.field("paths", &self.paths).finish()
     }
 }
@@ -136,6 +189,8 @@ impl<'de> Visitor<'de> for WatchVisitor {
                     .into_inner();
             } else if k == "max_depth" {
                 watch.max_depth = map.next_value()?;
+            } else if k == "on_change" {
+                watch.on_change = map.next_value()?;
             }
         }
         Ok(watch)
@@ -166,11 +221,17 @@ impl Serialize for Watch {
     where
         S: serde::Serializer,
     {
-        if self.include.is_none() && self.exclude.is_none() && self.max_depth == DEFAULT_MAX_DEPTH {
+        let on_change = (self.on_change != OnChange::default()).then_some(&self.on_change);
+        if self.include.is_none()
+            && self.exclude.is_none()
+            && self.max_depth == DEFAULT_MAX_DEPTH
+            && on_change.is_none()
+        {
             OneOrManyWrapper(&self.paths).serialize(serializer)
         } else {
-            let mut map =
-                serializer.serialize_map(Some(1 + self.include.len() + self.exclude.len()))?;
+            let mut map = serializer.serialize_map(Some(
+                1 + self.include.len() + self.exclude.len() + on_change.len(),
+            ))?;
             if let Some(include) = self.include.as_ref() {
                 map.serialize_entry("include", &include)?;
             }
@@ -180,6 +241,9 @@ impl Serialize for Watch {
             if self.max_depth != DEFAULT_MAX_DEPTH {
                 map.serialize_entry("max_depth", &self.max_depth)?;
             }
+            if let Some(on_change) = on_change {
+                map.serialize_entry("on_change", on_change)?;
+            }
             map.serialize_entry("paths", &OneOrManyWrapper(&self.paths))?;
             map.end()
         }
@@ -189,6 +253,7 @@ impl Serialize for Watch {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{service::Command, utils::signal::SIGHUP};
     use anyhow::{Result, anyhow};
     use serde_yaml_ng as yaml;
 
@@ -218,12 +283,36 @@ mod tests {
         assert_eq!(None, watch.include);
         assert_eq!(None, watch.exclude);
         assert!(watch.paths.is_empty());
+        assert_eq!(watch.on_change, OnChange::Restart);
+
+        let watch = yaml::from_str::<Watch>("{ paths: /tmp, on_change: { signal: SIGHUP } }")?;
+        assert_eq!(watch.on_change, OnChange::Signal(SIGHUP));
+        assert_eq!(watch, yaml::from_str(&yaml::to_string(&watch)?)?);
+
+        let watch = yaml::from_str::<Watch>(
+            "{ paths: /tmp, on_change: { run: { path: make, args: [build] } } }",
+        )?;
+        assert_eq!(
+            watch.on_change,
+            OnChange::Run(Hook::new(Command::new("make", ["build"])))
+        );
+        assert_eq!(watch, yaml::from_str(&yaml::to_string(&watch)?)?);
+        assert_eq!(
+            yaml::from_str::<Watch>("{ paths: /tmp, on_change: restart }")?,
+            yaml::from_str::<Watch>("/tmp")?
+        );
         Ok(())
     }
 
     #[test]
     fn invalid() -> Result<()> {
-        for test in ["paths: null", "paths: 32", "include: 32"] {
+        for test in [
+            "paths: null",
+            "paths: 32",
+            "include: 32",
+            "on_change: reload",
+            "on_change: { signal: SIGFOO }",
+        ] {
             if yaml::from_str::<Watch>(test).is_ok() {
                 Err(anyhow!("should have failed to parse `{test}`"))?;
             }
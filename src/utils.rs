@@ -27,7 +27,7 @@ use std::{
 };
 
 pub mod serde_utils;
-pub use serde_utils::{InnerRef, LoadFromFile, wrap_map_iterator};
+pub use serde_utils::{InnerRef, LoadFromFile, SaveToFile, wrap_map_iterator};
 
 pub mod poller;
 
@@ -29,6 +29,9 @@ use libc::{c_int, pid_t};
 mod fcntl;
 pub use fcntl::{Fcntl, FdFlags};
 
+mod user;
+pub use user::{Credentials, get_gid, get_uid};
+
 /// Set session-id
 ///
 /// Returns the new session-id
@@ -57,6 +60,11 @@ pub fn getpgid(pid: pid_t) -> pid_t {
 /// Invoke waitpid in non-blocking mode
 #[tracing::instrument(level = "TRACE", ret)]
 pub fn waitpid(pid: pid_t, blocking: bool) -> Option<(pid_t, c_int)> {
+    try_waitpid(pid, blocking).ok().flatten()
+}
+
+/// Invoke waitpid, reporting errors (ex: `ECHILD` when already reaped)
+pub fn try_waitpid(pid: pid_t, blocking: bool) -> std::io::Result<Option<(pid_t, c_int)>> {
     let mut status: c_int = 0;
     let ret = unsafe {
         libc::waitpid(
@@ -65,7 +73,38 @@ pub fn waitpid(pid: pid_t, blocking: bool) -> Option<(pid_t, c_int)> {
             if blocking { 0 } else { libc::WNOHANG } | libc::WUNTRACED, /* [libc::WUNTRACED] is required to detect SIGSTOP */
         )
     };
-    if ret > 0 { Some((ret, status)) } else { None }
+    match ret {
+        ret if ret > 0 => Ok(Some((ret, status))),
+        0 => Ok(None),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Get the next waitable child, without reaping it
+///
+/// `pid` has the same meaning as in [waitpid], stopped children are reported.
+pub fn waitpid_peek(pid: pid_t) -> Option<pid_t> {
+    let (idtype, id) = match pid {
+        -1 => (libc::P_ALL, 0),
+        0 => (libc::P_PGID, 0),
+        pid if pid < 0 => (libc::P_PGID, -pid),
+        pid => (libc::P_PID, pid),
+    };
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let ret = unsafe {
+        libc::waitid(
+            idtype,
+            id as libc::id_t,
+            &mut info,
+            libc::WEXITED | libc::WSTOPPED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    let child = unsafe { info.si_pid() };
+    if ret == 0 && child > 0 {
+        Some(child)
+    } else {
+        None
+    }
 }
 
 /// assert for libc functions
@@ -0,0 +1,198 @@
+/*
+** Copyright (C) 2026 Sylvain Fargier
+**
+** This software is provided 'as-is', without any express or implied
+** warranty.  In no event will the authors be held liable for any damages
+** arising from the use of this software.
+**
+** Permission is granted to anyone to use this software for any purpose,
+** including commercial applications, and to alter it and redistribute it
+** freely, subject to the following restrictions:
+**
+** 1. The origin of this software must not be misrepresented; you must not
+**    claim that you wrote the original software. If you use this software
+**    in a product, an acknowledgment in the product documentation would be
+**    appreciated but is not required.
+** 2. Altered source versions must be plainly marked as such, and must not be
+**    misrepresented as being the original software.
+** 3. This notice may not be removed or altered from any source distribution.
+**
+** Created on: 2026-10-16T16:11:37
+** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
+*/
+
+use std::ffi::CString;
+
+use super::check;
+use anyhow::{Result, anyhow};
+use libc::{gid_t, uid_t};
+
+/// Process credentials, applied by dropping privileges
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Credentials {
+    /// User name, supplementary groups default to its `/etc/group` memberships
+    pub user: Option<String>,
+    pub uid: Option<uid_t>,
+    pub gid: Option<gid_t>,
+    pub groups: Vec<gid_t>,
+}
+
+impl Credentials {
+    /// Resolve user and group names (or numeric ids)
+    ///
+    /// - `gid` defaults to the user's primary group, it is required for a
+    ///   numeric uid without passwd entry
+    pub fn resolve<S>(user: Option<&str>, group: Option<&str>, groups: &[S]) -> Result<Self>
+    where
+        S: AsRef<str>,
+    {
+        let uid = user.map(get_uid).transpose()?;
+        let passwd = uid.and_then(get_user);
+        let gid = match (group, uid, passwd.as_ref()) {
+            (Some(group), _, _) => Some(get_gid(group)?),
+            (None, Some(_), Some((_, gid))) => Some(*gid),
+            (None, Some(uid), None) => {
+                return Err(anyhow!(
+                    "no passwd entry for uid `{uid}`, a group is required"
+                ));
+            }
+            (None, None, _) => None,
+        };
+        Ok(Self {
+            user: passwd.map(|(name, _)| name),
+            uid,
+            gid,
+            groups: groups
+                .iter()
+                .map(|group| get_gid(group.as_ref()))
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.user.is_none() && self.uid.is_none() && self.gid.is_none() && self.groups.is_empty()
+    }
+
+    /// Drop privileges on current process
+    ///
+    /// Supplementary groups are replaced, not extended, they are initialized
+    /// from the user's group memberships when none is given.
+    pub fn apply(&self) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        match (self.user.as_ref(), self.gid) {
+            (Some(user), Some(gid)) if self.groups.is_empty() => {
+                let user = CString::new(user.as_str())?;
+                check(unsafe { libc::initgroups(user.as_ptr(), gid as _) })?;
+            }
+            _ => check(unsafe { libc::setgroups(self.groups.len() as _, self.groups.as_ptr()) })?,
+        }
+        if let Some(gid) = self.gid {
+            check(unsafe { libc::setgid(gid) })?;
+        }
+        if let Some(uid) = self.uid {
+            check(unsafe { libc::setuid(uid) })?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolve a user name or numeric id
+pub fn get_uid(user: &str) -> Result<uid_t> {
+    if let Ok(uid) = user.parse::<uid_t>() {
+        return Ok(uid);
+    }
+    let name = CString::new(user)?;
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let ret = unsafe {
+        libc::getpwnam_r(
+            name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if ret == 0 && !result.is_null() {
+        Ok(pwd.pw_uid)
+    } else {
+        Err(anyhow!("unknown user `{user}`"))
+    }
+}
+
+/// Get user's name and primary group
+pub fn get_user(uid: uid_t) -> Option<(String, gid_t)> {
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let ret = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    (ret == 0 && !result.is_null()).then(|| {
+        let name = unsafe { std::ffi::CStr::from_ptr(pwd.pw_name) };
+        (name.to_string_lossy().into_owned(), pwd.pw_gid)
+    })
+}
+
+/// Resolve a group name or numeric id
+pub fn get_gid(group: &str) -> Result<gid_t> {
+    if let Ok(gid) = group.parse::<gid_t>() {
+        return Ok(gid);
+    }
+    let name = CString::new(group)?;
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let ret = unsafe {
+        libc::getgrnam_r(
+            name.as_ptr(),
+            &mut grp,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if ret == 0 && !result.is_null() {
+        Ok(grp.gr_gid)
+    } else {
+        Err(anyhow!("unknown group `{group}`"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() -> Result<()> {
+        assert_eq!(get_uid("root")?, 0);
+        assert_eq!(get_uid("1234")?, 1234);
+        assert_eq!(get_gid("0")?, 0);
+        get_uid("ppm_no_such_user").expect_err("user should not exist");
+        get_gid("ppm_no_such_group").expect_err("group should not exist");
+
+        let creds = Credentials::resolve(Some("root"), None, &["0"])?;
+        assert_eq!(
+            creds,
+            Credentials {
+                user: Some("root".into()),
+                uid: Some(0),
+                gid: Some(0),
+                groups: vec![0]
+            }
+        );
+        assert!(Credentials::resolve::<&str>(None, None, &[])?.is_empty());
+
+        /* numeric uid without passwd entry requires a group */
+        let uid: uid_t = 3_999_999_999;
+        assert_eq!(get_user(uid), None);
+        let err = Credentials::resolve::<&str>(Some(&uid.to_string()), None, &[])
+            .expect_err("group should be required");
+        assert!(err.to_string().contains("group is required"), "{err}");
+        let creds = Credentials::resolve::<&str>(Some(&uid.to_string()), Some("1234"), &[])?;
+        assert_eq!(creds.user, None);
+        assert_eq!(creds.gid, Some(1234));
+        Ok(())
+    }
+}
@@ -56,6 +56,27 @@ pub mod size {
         ByteSize::deserialize(deserializer).map(|v| v.as_u64())
     }
 
+    /// Serializer for optional byte-sizes
+    pub mod option {
+        use super::Wrapper;
+        use bytesize::ByteSize;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S>(value: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            value.as_ref().map(Wrapper).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Option::<ByteSize>::deserialize(deserializer).map(|v| v.map(|v| v.as_u64()))
+        }
+    }
+
     pub struct Wrapper<'a>(pub &'a u64);
 
     impl Serialize for Wrapper<'_> {
@@ -30,6 +30,7 @@ use serde::{
 };
 
 use crate::{
+    monitor::dependencies,
     service::{SERVICE_ID_INVALID, Service, ServiceId},
     utils::serde_utils::{InnerRef, wrap_seq_iterator},
 };
@@ -40,8 +41,12 @@ pub fn serialize<S>(services: &ServiceMap, serializer: S) -> Result<S::Ok, S::Er
 where
     S: Serializer,
 {
-    /* we want it ordered, let's first iter on Ids */
-    let keys: BTreeSet<ServiceId> = services.iter().map(|it| *it.key()).collect();
+    /* we want it ordered, let's first iter on Ids, replicas are expanded from their parent */
+    let keys: BTreeSet<ServiceId> = services
+        .iter()
+        .filter(|it| it.replica.is_none())
+        .map(|it| *it.key())
+        .collect();
     wrap_seq_iterator(
         keys.iter()
             .filter_map(|key| services.get(key).map(|it| InnerRef(it, |r| r.value()))),
@@ -69,7 +74,7 @@ impl<'de> Visitor<'de> for ServiceMapVisitor {
     where
         A: serde::de::MapAccess<'de>,
     {
-        let map = ServiceMap::with_capacity(access.size_hint().unwrap_or(0));
+        let mut vec = Vec::<Service>::with_capacity(access.size_hint().unwrap_or(0));
 
         while let Some((key, mut value)) = access.next_entry::<ServiceId, Service>()? {
             if value.id != SERVICE_ID_INVALID && value.id != key {
@@ -79,10 +84,9 @@ impl<'de> Visitor<'de> for ServiceMapVisitor {
             } else {
                 value.id = key;
             }
-            value = value.validate().map_err(Error::custom)?;
-            map.insert(key, Arc::new(value));
+            vec.push(value.validate().map_err(Error::custom)?);
         }
-        Ok(map)
+        into_service_map(vec).map_err(Error::custom)
     }
 
     fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
@@ -94,15 +98,29 @@ impl<'de> Visitor<'de> for ServiceMapVisitor {
         while let Some(value) = access.next_element::<Service>()? {
             vec.push(value.validate().map_err(Error::custom)?);
         }
-        Ok(DashMap::from_iter(
-            vec.into_iter().map(|srv| (srv.id, Arc::new(srv))),
-        ))
+        into_service_map(vec).map_err(Error::custom)
     }
 }
 
+/// Expand replicas, check cross-services constraints and build the [ServiceMap]
+fn into_service_map(services: Vec<Service>) -> anyhow::Result<ServiceMap> {
+    let services: Vec<Service> = services
+        .into_iter()
+        .map(Service::expand)
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    dependencies::levels(&services)?;
+    Ok(DashMap::from_iter(
+        services.into_iter().map(|srv| (srv.id, Arc::new(srv))),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::service::Status;
     use anyhow::Result;
     use serde::{Deserialize, Serialize};
     use serde_yaml_ng as yaml;
@@ -133,6 +151,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn replicas() -> Result<()> {
+        let wrapper = yaml::from_str::<Wrapper>(
+            "services: [ { name: test, command: { path: ls }, instances: 3 } ]",
+        )?;
+        let mut names: Vec<String> = wrapper.services.iter().map(|s| s.name.clone()).collect();
+        names.sort();
+        assert_eq!(names, ["test", "test.1", "test.2"]);
+
+        let config = yaml::to_string(&wrapper)?;
+        assert_eq!(config.matches("name:").count(), 1, "{config}");
+        assert!(config.contains("instances: 3"), "{config}");
+        Ok(())
+    }
+
+    #[test]
+    fn autostart() -> Result<()> {
+        let wrapper = yaml::from_str::<Wrapper>(
+            "services: [ { name: test, command: { path: ls }, instances: 2, autostart: false } ]",
+        )?;
+        for service in wrapper.services.iter() {
+            assert!(!service.info().active, "{}", service.name);
+            assert_eq!(service.info().status, Status::Created);
+        }
+
+        let config = yaml::to_string(&wrapper)?;
+        assert!(config.contains("autostart: false"), "{config}");
+        Ok(())
+    }
+
     #[test]
     fn validation_errors() -> Result<()> {
         let data = [
@@ -142,6 +190,27 @@ mod tests {
                 "services: { 0: { id: 1, name: test, command: { path: ls } } }",
                 "id",
             ),
+            (
+                "services: [ { name: a, command: { path: ls }, depends_on: [b] },\
+                             { name: b, command: { path: ls }, depends_on: [a] } ]",
+                "cycle",
+            ),
+            (
+                "services: [ { name: a, command: { path: ls }, depends_on: [b] } ]",
+                "unknown dependency",
+            ),
+            (
+                "services: [ { name: a, command: { path: ls }, instances: 0 } ]",
+                "invalid `instances` for service `a`",
+            ),
+            (
+                "services: [ { name: a, command: { path: ls }, user: ppm_no_such_user } ]",
+                "unknown user `ppm_no_such_user` for service `a`",
+            ),
+            (
+                "services: [ { name: a, command: { path: ls }, supplementary_groups: [ppm_no_such_group] } ]",
+                "unknown group",
+            ),
         ];
         for test in data.into_iter() {
             let err = yaml::from_str::<Wrapper>(test.0).expect_err("should have failed to parse");
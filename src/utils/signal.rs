@@ -21,11 +21,13 @@
 ** Author: Sylvain Fargier <fargier.sylvain@gmail.com>
 */
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize, de::Error};
 use std::{
     fmt::Debug,
     ops::Deref,
     ptr::{null, null_mut},
+    str::FromStr,
     sync::LazyLock,
 };
 
@@ -57,6 +59,42 @@ pub const SIGCONT: Signal = Signal(libc::SIGCONT);
 pub const SIGKILL: Signal = Signal(libc::SIGKILL);
 pub const SIGINT: Signal = Signal(libc::SIGINT);
 pub const SIGHUP: Signal = Signal(libc::SIGHUP);
+pub const SIGQUIT: Signal = Signal(libc::SIGQUIT);
+pub const SIGUSR1: Signal = Signal(libc::SIGUSR1);
+pub const SIGUSR2: Signal = Signal(libc::SIGUSR2);
+
+/// Signal names, as used in configuration files and command-line
+const SIGNAL_NAMES: &[(&str, libc::c_int)] = &[
+    ("SIGHUP", libc::SIGHUP),
+    ("SIGINT", libc::SIGINT),
+    ("SIGQUIT", libc::SIGQUIT),
+    ("SIGILL", libc::SIGILL),
+    ("SIGTRAP", libc::SIGTRAP),
+    ("SIGABRT", libc::SIGABRT),
+    ("SIGBUS", libc::SIGBUS),
+    ("SIGFPE", libc::SIGFPE),
+    ("SIGKILL", libc::SIGKILL),
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGSEGV", libc::SIGSEGV),
+    ("SIGUSR2", libc::SIGUSR2),
+    ("SIGPIPE", libc::SIGPIPE),
+    ("SIGALRM", libc::SIGALRM),
+    ("SIGTERM", libc::SIGTERM),
+    ("SIGCHLD", libc::SIGCHLD),
+    ("SIGCONT", libc::SIGCONT),
+    ("SIGSTOP", libc::SIGSTOP),
+    ("SIGTSTP", libc::SIGTSTP),
+    ("SIGTTIN", libc::SIGTTIN),
+    ("SIGTTOU", libc::SIGTTOU),
+    ("SIGURG", libc::SIGURG),
+    ("SIGXCPU", libc::SIGXCPU),
+    ("SIGXFSZ", libc::SIGXFSZ),
+    ("SIGVTALRM", libc::SIGVTALRM),
+    ("SIGPROF", libc::SIGPROF),
+    ("SIGWINCH", libc::SIGWINCH),
+    ("SIGIO", libc::SIGIO),
+    ("SIGSYS", libc::SIGSYS),
+];
 
 static FULL_SET: LazyLock<SignalSet> = LazyLock::new(|| {
     SignalSet(unsafe {
@@ -118,6 +156,79 @@ impl Signal {
         let ret = unsafe { libc::signal(self.0, libc::SIG_IGN) };
         libc_check(if ret == libc::SIG_ERR { -1 } else { 0 })
     }
+
+    /// Highest signal number
+    pub fn max() -> libc::c_int {
+        #[cfg(target_os = "linux")]
+        return libc::SIGRTMAX();
+        #[cfg(target_os = "macos")]
+        return libc::SIGUSR2;
+    }
+
+    /// Build a signal from its number, in `1..=Signal::max()`
+    pub fn from_number(sig: libc::c_int) -> Result<Self> {
+        if (1..=Signal::max()).contains(&sig) {
+            Ok(Signal(sig))
+        } else {
+            Err(anyhow!("invalid signal number `{sig}`"))
+        }
+    }
+
+    /// Signal name (ex: `SIGTERM`)
+    pub fn name(&self) -> Option<&'static str> {
+        SIGNAL_NAMES
+            .iter()
+            .find(|(_, sig)| *sig == self.0)
+            .map(|(name, _)| *name)
+    }
+}
+
+impl FromStr for Signal {
+    type Err = anyhow::Error;
+
+    /// Parse a signal name (`SIGTERM`, `term`) or number
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(sig) = s.parse::<libc::c_int>() {
+            return Signal::from_number(sig);
+        }
+        let name = s.to_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+        SIGNAL_NAMES
+            .iter()
+            .find(|(n, _)| &n[3..] == name)
+            .map(|(_, sig)| Signal(*sig))
+            .ok_or_else(|| anyhow!("unknown signal `{s}`"))
+    }
+}
+
+impl Serialize for Signal {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.name() {
+            Some(name) => name.serialize(serializer),
+            None => self.0.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Signal {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum NameOrNumber {
+            Number(libc::c_int),
+            Name(String),
+        }
+        match NameOrNumber::deserialize(deserializer)? {
+            NameOrNumber::Number(sig) => Signal::from_number(sig).map_err(D::Error::custom),
+            NameOrNumber::Name(name) => name.parse().map_err(D::Error::custom),
+        }
+    }
 }
 
 impl Deref for Signal {
@@ -130,19 +241,10 @@ impl Deref for Signal {
 
 impl Debug for Signal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // f.write_str("Signal(")?;
-        match self.0 {
-            libc::SIGHUP => f.write_str("SIGHUP"),
-            libc::SIGALRM => f.write_str("SIGALRM"),
-            libc::SIGCHLD => f.write_str("SIGCHLD"),
-            libc::SIGTERM => f.write_str("SIGTERM"),
-            libc::SIGSTOP => f.write_str("SIGSTOP"),
-            libc::SIGCONT => f.write_str("SIGCONT"),
-            libc::SIGKILL => f.write_str("SIGKILL"),
-            libc::SIGINT => f.write_str("SIGINT"),
-            sig => write!(f, "SIG({})", sig),
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "SIG({})", self.0),
         }
-        // f.write_str(")")
     }
 }
 
@@ -411,6 +513,30 @@ mod tests {
         sigset.restore()
     }
 
+    #[test]
+    fn parse() -> Result<()> {
+        assert_eq!("SIGTERM".parse::<Signal>()?, SIGTERM);
+        assert_eq!("hup".parse::<Signal>()?, SIGHUP);
+        assert_eq!("Usr1".parse::<Signal>()?, SIGUSR1);
+        assert_eq!(libc::SIGKILL.to_string().parse::<Signal>()?, SIGKILL);
+        "SIGNOPE"
+            .parse::<Signal>()
+            .expect_err("should fail to parse");
+
+        assert_eq!(serde_yaml_ng::to_string(&SIGQUIT)?, "SIGQUIT\n");
+        assert_eq!(serde_yaml_ng::from_str::<Signal>("SIGQUIT")?, SIGQUIT);
+        assert_eq!(serde_yaml_ng::from_str::<Signal>("15")?, SIGTERM);
+        assert_eq!(
+            Signal::max().to_string().parse::<Signal>()?,
+            Signal(Signal::max())
+        );
+        for sig in ["0", "-9", &(Signal::max() + 1).to_string()] {
+            sig.parse::<Signal>().expect_err("out of range signal");
+            serde_yaml_ng::from_str::<Signal>(sig).expect_err("out of range signal");
+        }
+        Ok(())
+    }
+
     #[test]
     fn signalset() {
         let sigset = SignalSet::default() + SIGALRM + SIGCHLD;
@@ -115,7 +115,9 @@ mod tests {
             "stats",
             "statistics",
             "details",
+            "pause-scheduler",
             "show-scheduler",
+            "resume-scheduler",
         ] {
             assert!(
                 ppm().arg(cmd).status()?.success(),
@@ -124,7 +126,15 @@ mod tests {
             );
         }
 
-        for cmd in ["restart", "stop", "reschedule", "remove"] {
+        for cmd in [
+            "history",
+            "pause-scheduler",
+            "resume-scheduler",
+            "restart",
+            "stop",
+            "reschedule",
+            "remove",
+        ] {
             assert!(
                 ppm().arg(cmd).arg("test").status()?.success(),
                 "failed to run command: {:?}",